@@ -0,0 +1,74 @@
+//! Measures the time and the number of heap allocations
+//! needed to display custom errors.
+//!
+//! Run with `cargo bench`.
+
+#[macro_use]
+extern crate custom_error;
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::fmt::{self, Write};
+use std::hint::black_box;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Instant;
+
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+custom_error! {ParseError
+    Unexpected{found: char, line: usize, column: usize} = "unexpected {found:?} at {line}:{column}",
+    Overflow{value: u64, max: u64}                      = "{value:#x} is larger than {max}",
+    Eof(usize)                                          = "unexpected end of file after {0} bytes",
+    Empty                                               = "empty input",
+}
+
+/// A formatter output that discards everything that is written to it
+struct Discard;
+
+impl Write for Discard {
+    fn write_str(&mut self, _: &str) -> fmt::Result { Ok(()) }
+}
+
+const ITERATIONS: usize = 1_000_000;
+
+fn bench(name: &str, err: &ParseError) {
+    for _ in 0..ITERATIONS / 10 {
+        write!(Discard, "{}", black_box(err)).unwrap();
+    }
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed);
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        write!(Discard, "{}", black_box(err)).unwrap();
+    }
+    let elapsed = start.elapsed();
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed) - allocations;
+    println!(
+        "{:<12} {:>8.1} ns/iter {:>6} allocations",
+        name,
+        elapsed.as_nanos() as f64 / ITERATIONS as f64,
+        allocations
+    );
+    assert_eq!(0, allocations, "displaying {} allocated memory", name);
+}
+
+fn main() {
+    bench("unexpected", &ParseError::Unexpected { found: '}', line: 12, column: 7 });
+    bench("overflow", &ParseError::Overflow { value: u64::MAX, max: 255 });
+    bench("eof", &ParseError::Eof(1024));
+    bench("empty", &ParseError::Empty);
+}
@@ -0,0 +1,1300 @@
+//! Implementation of `#[derive(CustomError)]`.
+//!
+//! This crate is re-exported by the `custom_error` crate when its `derive` feature is enabled,
+//! and the code it generates refers to items of `custom_error`, through the path given with
+//! `#[custom_error(crate = path)]` (`::custom_error` by default), or through `$crate` for `methods!`.
+//! See the documentation of `custom_error::CustomError`.
+
+extern crate proc_macro;
+
+use proc_macro::{Delimiter, Group, Ident, Literal, Punct, Spacing, Span, TokenStream, TokenTree};
+use std::iter::FromIterator;
+
+/// Derives `Display`, `Error` and `From` for an enum or a struct,
+/// like the `custom_error!` macro does for the types it defines.
+#[proc_macro_derive(CustomError, attributes(custom_error, error, source, from, default, exit))]
+pub fn derive_custom_error(input: TokenStream) -> TokenStream {
+    match Input::parse(input) {
+        Ok(input) => replace_crate(input.expand().parse().expect("custom_error: invalid generated code"), &input.krate),
+        Err(error) => error.into_compile_error(),
+    }
+}
+
+/// Implements the methods named after the cases of the error types defined by `custom_error!`
+/// with the `#[accessors]` or `#[constructors]` options. Not part of the public API.
+#[doc(hidden)]
+#[proc_macro]
+pub fn methods(input: TokenStream) -> TokenStream {
+    match Methods::parse(input) {
+        Ok(methods) => replace_crate(methods.expand().parse().expect("custom_error: invalid generated code"), &methods.krate),
+        Err(error) => error.into_compile_error(),
+    }
+}
+
+/// The path of the `custom_error` crate in the generated code, replaced with the actual path by `replace_crate`
+const CRATE: &str = "__custom_error_crate";
+const PRIVATE: &str = "__custom_error_crate::private";
+
+/// Replaces the `CRATE` placeholder with the path of the `custom_error` crate
+fn replace_crate(stream: TokenStream, krate: &TokenStream) -> TokenStream {
+    stream
+        .into_iter()
+        .flat_map(|token| match token {
+            TokenTree::Ident(ref ident) if ident.to_string() == CRATE => krate.clone().into_iter().collect(),
+            TokenTree::Group(group) => {
+                let mut replaced = Group::new(group.delimiter(), replace_crate(group.stream(), krate));
+                replaced.set_span(group.span());
+                vec![TokenTree::Group(replaced)]
+            }
+            token => vec![token],
+        })
+        .collect()
+}
+
+/// An error in the input of the derive macro, reported with `compile_error!`
+struct Error {
+    span: Span,
+    message: String,
+}
+
+type Result<T> = std::result::Result<T, Error>;
+
+impl Error {
+    fn new<S: Into<String>>(span: Span, message: S) -> Self {
+        Error { span, message: message.into() }
+    }
+
+    fn into_compile_error(self) -> TokenStream {
+        let mut message = Literal::string(&self.message);
+        message.set_span(self.span);
+        let mut bang = Punct::new('!', Spacing::Alone);
+        bang.set_span(self.span);
+        let mut group = Group::new(Delimiter::Brace, TokenStream::from(TokenTree::Literal(message)));
+        group.set_span(self.span);
+        TokenStream::from_iter(vec![
+            TokenTree::Ident(Ident::new("compile_error", self.span)),
+            TokenTree::Punct(bang),
+            TokenTree::Group(group),
+        ])
+    }
+}
+
+fn to_string(tokens: &[TokenTree]) -> String {
+    TokenStream::from_iter(tokens.iter().cloned()).to_string()
+}
+
+fn is_punct(token: Option<&TokenTree>, c: char) -> bool {
+    match token {
+        Some(TokenTree::Punct(punct)) => punct.as_char() == c,
+        _ => false,
+    }
+}
+
+/// Splits a list of tokens on the commas that are not inside angle brackets
+fn split_commas(tokens: Vec<TokenTree>) -> Vec<Vec<TokenTree>> {
+    let mut parts = vec![Vec::new()];
+    let mut depth = 0usize;
+    let mut arrow = false;
+    for token in tokens {
+        if let TokenTree::Punct(ref punct) = token {
+            match punct.as_char() {
+                ',' if depth == 0 => {
+                    parts.push(Vec::new());
+                    continue;
+                }
+                '<' => depth += 1,
+                '>' if !arrow => depth = depth.saturating_sub(1),
+                _ => {}
+            }
+        }
+        arrow = match token {
+            TokenTree::Punct(ref punct) => punct.as_char() == '-' && punct.spacing() == Spacing::Joint,
+            _ => false,
+        };
+        parts.last_mut().unwrap().push(token);
+    }
+    parts.retain(|part| !part.is_empty());
+    parts
+}
+
+/// Returns the tokens that come before the first `=` that is not inside angle brackets
+fn before_default(tokens: &[TokenTree]) -> &[TokenTree] {
+    let mut depth = 0usize;
+    for (i, token) in tokens.iter().enumerate() {
+        if let TokenTree::Punct(ref punct) = *token {
+            match punct.as_char() {
+                '<' => depth += 1,
+                '>' => depth = depth.saturating_sub(1),
+                '=' if depth == 0 => return &tokens[..i],
+                _ => {}
+            }
+        }
+    }
+    tokens
+}
+
+struct Cursor {
+    tokens: Vec<TokenTree>,
+    position: usize,
+}
+
+impl Cursor {
+    fn new(tokens: TokenStream) -> Self {
+        Cursor { tokens: tokens.into_iter().collect(), position: 0 }
+    }
+
+    fn peek(&self) -> Option<&TokenTree> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Option<TokenTree> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    fn span(&self) -> Span {
+        self.peek().or_else(|| self.tokens.last()).map_or_else(Span::call_site, TokenTree::span)
+    }
+
+    fn is_ident(&self, name: &str) -> bool {
+        match self.peek() {
+            Some(TokenTree::Ident(ident)) => ident.to_string() == name,
+            _ => false,
+        }
+    }
+
+    fn is_group(&self, delimiter: Delimiter) -> bool {
+        match self.peek() {
+            Some(TokenTree::Group(group)) => group.delimiter() == delimiter,
+            _ => false,
+        }
+    }
+
+    fn group(&mut self) -> Result<Group> {
+        match self.next() {
+            Some(TokenTree::Group(group)) => Ok(group),
+            _ => Err(Error::new(self.span(), "custom_error: expected a group")),
+        }
+    }
+
+    fn ident(&mut self) -> Result<Ident> {
+        match self.next() {
+            Some(TokenTree::Ident(ident)) => Ok(ident),
+            _ => Err(Error::new(self.span(), "custom_error: expected an identifier")),
+        }
+    }
+
+    fn rest(&mut self) -> Vec<TokenTree> {
+        let rest = self.tokens[self.position.min(self.tokens.len())..].to_vec();
+        self.position = self.tokens.len();
+        rest
+    }
+
+    /// Parses the outer attributes at the cursor
+    fn attributes(&mut self) -> Vec<Attribute> {
+        let mut attributes = Vec::new();
+        while is_punct(self.peek(), '#') {
+            let span = self.span();
+            self.next();
+            if let Some(TokenTree::Group(group)) = self.next() {
+                let mut tokens = group.stream().into_iter();
+                let name = tokens.next().map(|name| name.to_string()).unwrap_or_default();
+                attributes.push(Attribute { name, span, arguments: tokens.collect() });
+            }
+        }
+        attributes
+    }
+
+    /// Skips a visibility, such as `pub` or `pub(crate)`
+    fn visibility(&mut self) {
+        if self.is_ident("pub") {
+            self.next();
+            if self.is_group(Delimiter::Parenthesis) {
+                self.next();
+            }
+        }
+    }
+
+    /// Parses a where clause, and returns its predicates
+    fn where_clause(&mut self) -> Vec<TokenTree> {
+        let mut predicates = Vec::new();
+        if self.is_ident("where") {
+            self.next();
+            while self.peek().is_some() && !self.is_group(Delimiter::Brace) && !is_punct(self.peek(), ';') {
+                predicates.extend(self.next());
+            }
+        }
+        predicates
+    }
+}
+
+struct Attribute {
+    name: String,
+    span: Span,
+    arguments: Vec<TokenTree>,
+}
+
+/// A generic parameter of the error type
+struct Param {
+    /// The name of the parameter, used as a generic argument of the type
+    name: String,
+    /// The declaration of the parameter, without its default value
+    declaration: String,
+    /// False for lifetime parameters
+    is_type: bool,
+}
+
+struct Generics {
+    params: Vec<Param>,
+    predicates: String,
+}
+
+impl Generics {
+    fn parse(cursor: &mut Cursor) -> Generics {
+        let mut tokens = Vec::new();
+        if is_punct(cursor.peek(), '<') {
+            cursor.next();
+            let mut depth = 1;
+            let mut arrow = false;
+            while let Some(token) = cursor.next() {
+                if let TokenTree::Punct(ref punct) = token {
+                    match punct.as_char() {
+                        '<' => depth += 1,
+                        '>' if !arrow => depth -= 1,
+                        _ => {}
+                    }
+                    if depth == 0 {
+                        break;
+                    }
+                }
+                arrow = match token {
+                    TokenTree::Punct(ref punct) => punct.as_char() == '-' && punct.spacing() == Spacing::Joint,
+                    _ => false,
+                };
+                tokens.push(token);
+            }
+        }
+        let params = split_commas(tokens)
+            .into_iter()
+            .map(|tokens| {
+                let declaration = to_string(before_default(&tokens));
+                match (&tokens[0], tokens.get(1)) {
+                    (TokenTree::Punct(_), _) => Param { name: to_string(&tokens[..2]), declaration, is_type: false },
+                    (TokenTree::Ident(ident), Some(name)) if ident.to_string() == "const" => {
+                        Param { name: name.to_string(), declaration, is_type: true }
+                    }
+                    (name, _) => Param { name: name.to_string(), declaration, is_type: true },
+                }
+            })
+            .collect();
+        Generics { params, predicates: String::new() }
+    }
+
+    /// Returns true if the tokens reference one of the type or const parameters
+    fn is_used_in(&self, tokens: &[TokenTree]) -> bool {
+        let mut lifetime = false;
+        tokens.iter().any(|token| {
+            let used = match *token {
+                TokenTree::Ident(ref ident) => {
+                    !lifetime && self.params.iter().any(|param| param.is_type && param.name == ident.to_string())
+                }
+                TokenTree::Group(ref group) => self.is_used_in(&group.stream().into_iter().collect::<Vec<_>>()),
+                _ => false,
+            };
+            lifetime = is_punct(Some(token), '\'');
+            used
+        })
+    }
+
+    fn impl_params(&self) -> String {
+        if self.params.is_empty() {
+            return String::new();
+        }
+        let declarations: Vec<&str> = self.params.iter().map(|param| param.declaration.as_str()).collect();
+        format!("<{}>", declarations.join(", "))
+    }
+
+    fn type_args(&self) -> String {
+        if self.params.is_empty() {
+            return String::new();
+        }
+        let names: Vec<&str> = self.params.iter().map(|param| param.name.as_str()).collect();
+        format!("<{}>", names.join(", "))
+    }
+
+    /// Returns the where clause of the type, with additional bounds
+    fn where_clause(&self, bounds: &[String]) -> String {
+        let mut predicates = Vec::new();
+        let own = self.predicates.trim().trim_end_matches(',');
+        if !own.is_empty() {
+            predicates.push(own);
+        }
+        predicates.extend(bounds.iter().map(String::as_str));
+        if predicates.is_empty() {
+            String::new()
+        } else {
+            format!("where {}", predicates.join(", "))
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Style {
+    Unit,
+    Named,
+    Tuple,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Role {
+    /// The field is the source of the error
+    Source,
+    /// The field is the source of the error if its type implements Error
+    MaybeSource,
+    Attr,
+}
+
+/// How a field is initialized when the error is converted from its source
+enum Value {
+    Default,
+    Location,
+    Backtrace,
+    Expr(String),
+}
+
+struct Field {
+    /// The name of the field, or its position in a tuple
+    member: String,
+    /// The name of the variable the field is bound to in patterns
+    binding: String,
+    ty: Vec<TokenTree>,
+    span: Span,
+    role: Role,
+    from: bool,
+    value: Value,
+}
+
+impl Field {
+    fn parse(tokens: Vec<TokenTree>, style: Style, index: usize) -> Result<(Field, Vec<Attribute>)> {
+        let mut cursor = Cursor::new(TokenStream::from_iter(tokens));
+        let attributes = cursor.attributes();
+        cursor.visibility();
+        let span = cursor.span();
+        let (member, binding) = if style == Style::Named {
+            let name = cursor.ident()?.to_string();
+            if !is_punct(cursor.next().as_ref(), ':') {
+                return Err(Error::new(span, "custom_error: expected `:` after the field name"));
+            }
+            (name.trim_start_matches("r#").to_string(), name)
+        } else {
+            (index.to_string(), format!("_{}", index))
+        };
+        let ty = cursor.rest();
+        let field = Field { member, binding, ty, span, role: Role::Attr, from: false, value: Value::Default };
+        Ok((field, attributes))
+    }
+
+    fn type_name(&self) -> String {
+        to_string(&self.ty)
+    }
+
+    /// Returns the value of fields whose type is a location or a backtrace
+    fn captured_value(&self) -> Value {
+        let ty: String = self.type_name().chars().filter(|c| !c.is_whitespace()).collect();
+        let ty = ty.trim_start_matches("::");
+        let location = ["Location", "std::panic::Location", "core::panic::Location"];
+        let backtrace = ["Backtrace", "std::backtrace::Backtrace"];
+        if location.iter().any(|path| ty == format!("&'static{}<'static>", path)) {
+            Value::Location
+        } else if backtrace.contains(&ty) {
+            Value::Backtrace
+        } else {
+            Value::Default
+        }
+    }
+}
+
+enum Message {
+    /// The message, as the source code of a string literal whose placeholders
+    /// reference the variables bound to the fields
+    Format { literal: String, uses: Vec<(usize, &'static str)> },
+    Transparent(Span),
+}
+
+struct Variant {
+    name: String,
+    /// The path used to construct and match the variant
+    path: String,
+    style: Style,
+    fields: Vec<Field>,
+    message: Message,
+    /// The exit code given with `#[exit = code]`
+    exit: Option<String>,
+}
+
+impl Variant {
+    fn parse(
+        name: &Ident,
+        path: String,
+        attributes: &[Attribute],
+        body: Option<Group>,
+        generics: &Generics,
+    ) -> Result<Variant> {
+        let (style, tokens) = match body {
+            Some(ref group) if group.delimiter() == Delimiter::Brace => (Style::Named, group.stream()),
+            Some(ref group) if group.delimiter() == Delimiter::Parenthesis => (Style::Tuple, group.stream()),
+            _ => (Style::Unit, TokenStream::new()),
+        };
+        let mut fields = Vec::new();
+        let mut markers = Vec::new();
+        for (index, tokens) in split_commas(tokens.into_iter().collect()).into_iter().enumerate() {
+            let (field, attributes) = Field::parse(tokens, style, index)?;
+            fields.push(field);
+            markers.push(attributes);
+        }
+        let name = name.to_string();
+        let explicit_from = markers.iter().flatten().any(|attribute| attribute.name == "from");
+        let single = style == Style::Tuple && fields.len() == 1;
+        for (field, attributes) in fields.iter_mut().zip(markers) {
+            let generic = generics.is_used_in(&field.ty);
+            let marker = |name: &str| attributes.iter().find(|attribute| attribute.name == name);
+            field.value = field.captured_value();
+            if let Some(default) = marker("default") {
+                field.value = Value::Expr(match default.arguments.split_first() {
+                    Some((TokenTree::Group(group), [])) => group.stream().to_string(),
+                    Some((eq, value)) if is_punct(Some(eq), '=') => to_string(value),
+                    _ => return Err(Error::new(default.span, "custom_error: expected #[default = value]")),
+                });
+            }
+            if marker("from").is_some() {
+                field.role = Role::Source;
+                field.from = true;
+            } else if marker("source").is_some() {
+                field.role = Role::Source;
+            } else if style == Style::Named && field.member == "source" {
+                field.role = Role::Source;
+                field.from = !explicit_from && !generic;
+            } else if single && !generic {
+                field.role = Role::MaybeSource;
+            }
+        }
+        // A field named `source` is only converted from if the other fields have a value or are captured
+        if !explicit_from
+            && fields.iter().any(|field| field.from && field.member == "source")
+            && fields.iter().any(|field| !field.from && matches!(field.value, Value::Default))
+        {
+            for field in &mut fields {
+                field.from = false;
+            }
+        }
+        if let Some(field) = fields.iter().filter(|field| field.from).nth(1) {
+            return Err(Error::new(field.span, format!(
+                "custom_error: only one field of `{}` can be converted from with #[from]", name
+            )));
+        }
+        let message = Variant::message(&name, attributes, &fields)?;
+        if let Message::Transparent(span) = message {
+            if fields.len() != 1 {
+                return Err(Error::new(span, format!(
+                    "custom_error: the transparent variant `{}` must have a single attribute", name
+                )));
+            }
+        }
+        let exit = match attributes.iter().find(|attribute| attribute.name == "exit") {
+            Some(attribute) => match attribute.arguments.split_first() {
+                Some((eq, code)) if is_punct(Some(eq), '=') && !code.is_empty() => Some(to_string(code)),
+                _ => return Err(Error::new(attribute.span, "custom_error: expected #[exit = code]")),
+            },
+            None => None,
+        };
+        Ok(Variant { name, path, style, fields, message, exit })
+    }
+
+    fn message(name: &str, attributes: &[Attribute], fields: &[Field]) -> Result<Message> {
+        let attribute = attributes.iter().find(|attribute| attribute.name == "error");
+        let attribute = attribute.ok_or_else(|| Error::new(
+            attributes.first().map_or_else(Span::call_site, |attribute| attribute.span),
+            format!("custom_error: missing #[error(\"...\")] attribute on `{}`", name),
+        ))?;
+        let tokens = match attribute.arguments.first() {
+            Some(TokenTree::Group(group)) if group.delimiter() == Delimiter::Parenthesis => {
+                group.stream().into_iter().collect::<Vec<_>>()
+            }
+            _ => Vec::new(),
+        };
+        match tokens.first() {
+            Some(TokenTree::Literal(literal)) if tokens.len() == 1 => {
+                let (literal, uses) = rewrite_message(literal, name, fields)?;
+                Ok(Message::Format { literal, uses })
+            }
+            Some(TokenTree::Ident(ident)) if tokens.len() == 1 && ident.to_string() == "transparent" => {
+                Ok(Message::Transparent(ident.span()))
+            }
+            _ => Err(Error::new(
+                attribute.span,
+                "custom_error: expected #[error(\"message\")] or #[error(transparent)]",
+            )),
+        }
+    }
+
+    /// The pattern that matches the variant and binds its fields
+    fn pattern(&self) -> String {
+        format!("{}{}", self.path, self.bindings())
+    }
+
+    fn bindings(&self) -> String {
+        let bindings: Vec<&str> = self.fields.iter().map(|field| field.binding.as_str()).collect();
+        match self.style {
+            Style::Unit => String::new(),
+            Style::Named => format!(" {{ {} }}", bindings.join(", ")),
+            Style::Tuple => format!("({})", bindings.join(", ")),
+        }
+    }
+}
+
+/// Rewrites the placeholders of an error message, so that they reference the variables
+/// bound to the fields of the variant, and returns the fields used with their formatting trait.
+/// Positional placeholders such as `{0}` or `{}` are rewritten to `{_0}`,
+/// and precisions taken from the next positional argument (`{:.*}`) to `{_1:._0$}`.
+fn rewrite_message(literal: &Literal, variant: &str, fields: &[Field]) -> Result<(String, Vec<(usize, &'static str)>)> {
+    let text = literal.to_string();
+    let (prefix, content, suffix, escapes) = if text.starts_with('"') && text.len() >= 2 {
+        ("\"", &text[1..text.len() - 1], "\"", true)
+    } else if text.starts_with("r#") || text.starts_with("r\"") {
+        let hashes = text[1..].chars().take_while(|&c| c == '#').count();
+        (&text[..hashes + 2], &text[hashes + 2..text.len() - hashes - 1], &text[text.len() - hashes - 1..], false)
+    } else {
+        return Err(Error::new(literal.span(), "custom_error: the error message must be a string literal"));
+    };
+    let find = |argument: &str| -> Result<(usize, &Field)> {
+        fields.iter().enumerate().find(|&(_, field)| field.member == argument).ok_or_else(|| {
+            let mut message = format!(
+                "custom_error: unknown placeholder `{{{}}}` in the message of `{}`", argument, variant
+            );
+            if fields.is_empty() {
+                message.push_str(", which has no fields");
+            } else {
+                let names: Vec<&str> = fields.iter().map(|field| field.member.as_str()).collect();
+                message.push_str("; available fields: ");
+                message.push_str(&names.join(", "));
+            }
+            Error::new(literal.span(), message)
+        })
+    };
+    let mut output = String::from(prefix);
+    let mut uses = Vec::new();
+    let mut implicit = 0;
+    let mut chars = content.chars().peekable();
+    while let Some(c) = chars.next() {
+        output.push(c);
+        if c == '\\' && escapes {
+            // Copy escape sequences, that may contain braces, unchanged
+            if let Some(escaped) = chars.next() {
+                output.push(escaped);
+                if escaped == 'u' {
+                    for c in chars.by_ref() {
+                        output.push(c);
+                        if c == '}' { break; }
+                    }
+                }
+            }
+        } else if (c == '{' || c == '}') && chars.peek() == Some(&c) {
+            output.push(chars.next().unwrap());
+        } else if c == '{' {
+            let mut argument = String::new();
+            while let Some(&c) = chars.peek() {
+                if c == ':' || c == '}' { break; }
+                argument.push(c);
+                chars.next();
+            }
+            let mut spec = String::new();
+            if chars.peek() == Some(&':') {
+                chars.next();
+                while let Some(&c) = chars.peek() {
+                    if c == '}' { break; }
+                    if c == '$' {
+                        // Width and precision arguments, such as `{x:width$}`
+                        let start = spec
+                            .char_indices()
+                            .rev()
+                            .take_while(|&(_, c)| c.is_alphanumeric() || c == '_')
+                            .last()
+                            .map_or(spec.len(), |(i, _)| i);
+                        let mut name = spec.split_off(start);
+                        if name.starts_with(|c: char| c.is_ascii_digit()) && !name.chars().all(|c| c.is_ascii_digit()) {
+                            // a leading zero is the `0` flag
+                            let digits = name.chars().take_while(char::is_ascii_digit).count();
+                            spec.push_str(&name[..digits]);
+                            name = name[digits..].to_string();
+                        }
+                        if !name.is_empty() {
+                            spec.push_str(&find(&name)?.1.binding);
+                        }
+                    } else if c == '*' && spec.ends_with('.') {
+                        // The precision is the next positional argument, before the value
+                        spec.push_str(&find(&implicit.to_string())?.1.binding);
+                        spec.push('$');
+                        implicit += 1;
+                        chars.next();
+                        continue;
+                    }
+                    spec.push(c);
+                    chars.next();
+                }
+            }
+            if argument.is_empty() {
+                argument = implicit.to_string();
+                implicit += 1;
+            }
+            let (index, field) = find(argument.trim())?;
+            let format_trait = match spec.chars().last() {
+                Some('?') => "Debug",
+                Some('x') => "LowerHex",
+                Some('X') => "UpperHex",
+                Some('o') => "Octal",
+                Some('b') => "Binary",
+                Some('e') => "LowerExp",
+                Some('E') => "UpperExp",
+                Some('p') => "Pointer",
+                _ => "Display",
+            };
+            uses.push((index, format_trait));
+            output.push_str(field.binding.trim_start_matches("r#"));
+            if !spec.is_empty() {
+                output.push(':');
+                output.push_str(&spec);
+            }
+        }
+    }
+    output.push_str(suffix);
+    Ok((output, uses))
+}
+
+struct Input {
+    /// The path of the `custom_error` crate, given with `#[custom_error(crate = path)]`
+    krate: TokenStream,
+    name: String,
+    generics: Generics,
+    variants: Vec<Variant>,
+}
+
+impl Input {
+    fn parse(input: TokenStream) -> Result<Input> {
+        let mut cursor = Cursor::new(input);
+        let attributes = cursor.attributes();
+        cursor.visibility();
+        let keyword = cursor.ident()?;
+        let name = cursor.ident()?;
+        let mut generics = Generics::parse(&mut cursor);
+        let mut predicates = cursor.where_clause();
+        let body = match cursor.next() {
+            Some(TokenTree::Group(group)) => Some(group),
+            _ => None,
+        };
+        let mut variants = Vec::new();
+        match keyword.to_string().as_str() {
+            "struct" => {
+                if predicates.is_empty() {
+                    predicates = cursor.where_clause();
+                }
+                generics.predicates = to_string(&predicates);
+                variants.push(Variant::parse(&name, "Self".into(), &attributes, body, &generics)?);
+            }
+            "enum" => {
+                generics.predicates = to_string(&predicates);
+                if let Some(attribute) = attributes.iter().find(|attribute| attribute.name == "error") {
+                    return Err(Error::new(
+                        attribute.span,
+                        "custom_error: the #[error] attribute goes on each variant of an enum",
+                    ));
+                }
+                let body = body.map_or_else(TokenStream::new, |body| body.stream());
+                for tokens in split_commas(body.into_iter().collect()) {
+                    let mut cursor = Cursor::new(TokenStream::from_iter(tokens));
+                    let attributes = cursor.attributes();
+                    let variant = cursor.ident()?;
+                    let fields = match cursor.next() {
+                        Some(TokenTree::Group(group)) => Some(group),
+                        _ => None,
+                    };
+                    let path = format!("Self::{}", variant);
+                    variants.push(Variant::parse(&variant, path, &attributes, fields, &generics)?);
+                }
+            }
+            _ => {
+                return Err(Error::new(keyword.span(), "custom_error: CustomError can only be derived for enums and structs"));
+            }
+        }
+        let krate = match attributes.iter().find(|attribute| attribute.name == "custom_error") {
+            Some(attribute) => Input::crate_path(attribute)?,
+            None => "::custom_error".parse().unwrap(),
+        };
+        Ok(Input { krate, name: name.to_string(), generics, variants })
+    }
+
+    /// Parses `#[custom_error(crate = path)]`
+    fn crate_path(attribute: &Attribute) -> Result<TokenStream> {
+        if let Some(TokenTree::Group(group)) = attribute.arguments.first() {
+            let tokens: Vec<TokenTree> = group.stream().into_iter().collect();
+            if let [TokenTree::Ident(ref keyword), ref eq, ref path @ ..] = tokens[..] {
+                if keyword.to_string() == "crate" && is_punct(Some(eq), '=') && !path.is_empty() {
+                    return Ok(TokenStream::from_iter(path.iter().cloned()));
+                }
+            }
+        }
+        Err(Error::new(attribute.span, "custom_error: expected #[custom_error(crate = path)]"))
+    }
+
+    fn expand(&self) -> String {
+        let mut output = self.impl_display();
+        output.push_str(&self.impl_error());
+        output.push_str(&self.impl_backtrace());
+        output.push_str(&self.impl_exit());
+        for variant in &self.variants {
+            output.push_str(&self.impl_from(variant));
+        }
+        output
+    }
+
+    fn impl_header(&self, bounds: &[String], trait_name: &str) -> String {
+        format!(
+            "#[allow(deprecated)] impl{} {} for {}{} {}",
+            self.generics.impl_params(),
+            trait_name,
+            self.name,
+            self.generics.type_args(),
+            self.generics.where_clause(bounds),
+        )
+    }
+
+    /// Adds a bound on the type of a field, if it depends on the generic parameters
+    fn add_bound(&self, bounds: &mut Vec<String>, field: &Field, bound: &str) {
+        let bound = format!("{}: {}", field.type_name(), bound);
+        if self.generics.is_used_in(&field.ty) && !bounds.contains(&bound) {
+            bounds.push(bound);
+        }
+    }
+
+    fn arms<F: Fn(&Variant) -> String>(&self, arm: F) -> String {
+        if self.variants.is_empty() {
+            return "match *self {}".into();
+        }
+        let arms: Vec<String> = self
+            .variants
+            .iter()
+            .map(|variant| format!("{} => {{ {} }}", variant.pattern(), arm(variant)))
+            .collect();
+        format!("match self {{ {} }}", arms.join(" "))
+    }
+
+    fn impl_display(&self) -> String {
+        let mut bounds = Vec::new();
+        for variant in &self.variants {
+            match variant.message {
+                Message::Format { ref uses, .. } => for &(index, format_trait) in uses {
+                    let bound = format!("{}::fmt::{}", PRIVATE, format_trait);
+                    self.add_bound(&mut bounds, &variant.fields[index], &bound);
+                },
+                Message::Transparent(_) => {
+                    self.add_bound(&mut bounds, &variant.fields[0], &format!("{}::fmt::Display", PRIVATE));
+                }
+            }
+        }
+        format!(
+            "{} {{ fn fmt(&self, __formatter: &mut {private}::fmt::Formatter) -> {private}::fmt::Result {{
+                #[allow(unused_variables)] {} }} }}",
+            self.impl_header(&bounds, &format!("{}::fmt::Display", PRIVATE)),
+            self.arms(|variant| match variant.message {
+                Message::Format { ref literal, .. } => format!("write!(__formatter, {})", literal),
+                Message::Transparent(_) => format!(
+                    "{}::fmt::Display::fmt({}, __formatter)", PRIVATE, variant.fields[0].binding
+                ),
+            }),
+            private = PRIVATE,
+        )
+    }
+
+    fn impl_error(&self) -> String {
+        let mut bounds = Vec::new();
+        if !self.generics.params.is_empty() {
+            bounds.push(format!(
+                "{}{}: {private}::fmt::Debug + {private}::fmt::Display",
+                self.name,
+                self.generics.type_args(),
+                private = PRIVATE
+            ));
+        }
+        let mut provided = Vec::new();
+        for variant in &self.variants {
+            let transparent = matches!(variant.message, Message::Transparent(_));
+            for field in &variant.fields {
+                if field.role == Role::Source || transparent {
+                    self.add_bound(&mut bounds, field, &format!("{}::AsDynError", PRIVATE));
+                }
+            }
+            let conversions: Vec<String> = variant
+                .fields
+                .iter()
+                .map(|field| match (field.role, &field.value) {
+                    (Role::Attr, &Value::Backtrace) => format!("(backtrace {})", field.binding),
+                    _ => format!("(attr {})", field.binding),
+                })
+                .collect();
+            provided.push(format!(
+                "{{ [] ({}) ({}) [{}] }}",
+                variant.path,
+                variant.bindings(),
+                conversions.join(" ")
+            ));
+        }
+        format!(
+            "{} {{ fn source(&self) -> Option<&(dyn ({private}::Error) + 'static)> {{
+                #[allow(unused_variables, unreachable_code)] {} }}
+                {krate}::impl_provide!{{ {} }} }}",
+            self.impl_header(&bounds, &format!("{}::Error", PRIVATE)),
+            self.arms(|variant| {
+                let mut body = String::new();
+                if let Message::Transparent(_) = variant.message {
+                    body.push_str(&format!(
+                        "{}::return_if_transparent!([transparent] {} {});",
+                        CRATE, variant.name, variant.fields[0].binding
+                    ));
+                }
+                for field in &variant.fields {
+                    match field.role {
+                        Role::Source => body.push_str(&format!("{}::return_if_source!(source, {});", CRATE, field.binding)),
+                        Role::MaybeSource => body.push_str(&format!("{}::return_if_source!(maybe_source, {});", CRATE, field.binding)),
+                        Role::Attr => {}
+                    }
+                }
+                body + "None"
+            }),
+            provided.join(" "),
+            private = PRIVATE,
+            krate = CRATE,
+        )
+    }
+
+    fn impl_backtrace(&self) -> String {
+        let backtrace = |variant: &Variant| {
+            variant
+                .fields
+                .iter()
+                .find(|field| matches!((field.role, &field.value), (Role::Attr, &Value::Backtrace)))
+                .map(|field| field.binding.clone())
+        };
+        if self.variants.iter().all(|variant| backtrace(variant).is_none()) {
+            return String::new();
+        }
+        format!(
+            "#[allow(deprecated, dead_code)] impl{} {}{} {} {{
+                /// Returns the backtrace captured when this error was created, if any
+                pub fn backtrace(&self) -> Option<&{}::Backtrace> {{ #[allow(unused_variables)] {} }} }}",
+            self.generics.impl_params(),
+            self.name,
+            self.generics.type_args(),
+            self.generics.where_clause(&[]),
+            PRIVATE,
+            self.arms(|variant| backtrace(variant).map_or_else(|| "None".into(), |field| format!("Some({})", field))),
+        )
+    }
+
+    /// Implements `exit_code()` and the `Exit` trait. Variants without `#[exit = code]` exit with 1.
+    fn impl_exit(&self) -> String {
+        format!(
+            "#[allow(deprecated, dead_code)] impl{} {}{} {} {{
+                /// Returns the exit code of the process when it fails with this error
+                pub fn exit_code(&self) -> i32 {{ #[allow(unused_variables)] {} }} }}
+            {} {{ fn exit_code(&self) -> i32 {{ Self::exit_code(self) }} }}",
+            self.generics.impl_params(),
+            self.name,
+            self.generics.type_args(),
+            self.generics.where_clause(&[]),
+            self.arms(|variant| variant.exit.clone().unwrap_or_else(|| "1".into())),
+            self.impl_header(&[], &format!("{}::Exit", CRATE)),
+        )
+    }
+
+    /// Implements the conversion from the type of the field marked with `from`, if any.
+    /// The other fields are captured, or initialized with their default value.
+    fn impl_from(&self, variant: &Variant) -> String {
+        let source = match variant.fields.iter().find(|field| field.from) {
+            Some(field) => field,
+            None => return String::new(),
+        };
+        let mut bounds = Vec::new();
+        let values: Vec<String> = variant
+            .fields
+            .iter()
+            .map(|field| {
+                let value = if field.from {
+                    "source".to_string()
+                } else {
+                    match field.value {
+                        Value::Default => {
+                            bounds.push(format!("for<'source> {}: Default", field.type_name()));
+                            "Default::default()".to_string()
+                        }
+                        Value::Location => format!("{}::Location::caller()", PRIVATE),
+                        Value::Backtrace => format!("{}::Backtrace::capture()", PRIVATE),
+                        Value::Expr(ref expr) => expr.clone(),
+                    }
+                };
+                match variant.style {
+                    Style::Named => format!("{}: {}", field.binding, value),
+                    _ => value,
+                }
+            })
+            .collect();
+        let value = match variant.style {
+            Style::Named => format!("{} {{ {} }}", variant.path, values.join(", ")),
+            _ => format!("{}({})", variant.path, values.join(", ")),
+        };
+        format!(
+            "{} {{ #[track_caller] fn from(source: {}) -> Self {{ {} }} }}",
+            self.impl_header(&bounds, &format!("From<{}>", source.type_name())),
+            source.type_name(),
+            value,
+        )
+    }
+}
+
+/// Converts the name of a variant to snake case: `NotFound` becomes `not_found`,
+/// and `HTTPError` becomes `http_error`
+fn snake_case(name: &str) -> String {
+    let chars: Vec<char> = name.chars().collect();
+    let mut snake = String::new();
+    for (i, &c) in chars.iter().enumerate() {
+        if c.is_uppercase() {
+            let previous = if i > 0 { chars[i - 1] } else { '_' };
+            let next_is_lower = chars.get(i + 1).is_some_and(|next| next.is_lowercase());
+            if previous.is_lowercase() || previous.is_ascii_digit() || (previous.is_uppercase() && next_is_lower) {
+                snake.push('_');
+            }
+            snake.extend(c.to_lowercase());
+        } else {
+            snake.push(c);
+        }
+    }
+    snake
+}
+
+/// Types that are passed as they are to constructors, instead of with `impl Into<Type>`,
+/// so that literals and references keep their inferred types
+const PLAIN_TYPES: &[&str] = &[
+    "bool", "char", "f32", "f64", "i8", "i16", "i32", "i64", "i128", "isize",
+    "u8", "u16", "u32", "u64", "u128", "usize",
+];
+
+/// A field of a case of an error type, in the input of the `methods!` macro
+struct MethodField {
+    /// The name of the field, or its position in a tuple
+    member: String,
+    ty: Vec<TokenTree>,
+    /// Whether the field is the source of the error (`source`), may be (`maybe_source`) or is not (`attr`)
+    kind: String,
+    /// How the field is initialized when the error is converted from another type:
+    /// `location` and `backtrace` fields are also captured by constructors
+    conversion: String,
+}
+
+impl MethodField {
+    fn parse(group: Group) -> Result<MethodField> {
+        let mut cursor = Cursor::new(group.stream());
+        let kind = cursor.next().map(|token| token.to_string()).unwrap_or_default();
+        let conversion = cursor.next().map(|token| token.to_string()).unwrap_or_default();
+        let member = cursor.next().map(|token| token.to_string()).unwrap_or_default();
+        if !is_punct(cursor.next().as_ref(), ':') {
+            return Err(Error::new(group.span(), "custom_error: invalid input for methods!"));
+        }
+        let ty = cursor.rest();
+        Ok(MethodField { member, ty, kind, conversion })
+    }
+
+    /// The name of the parameter of constructors for this field
+    fn param(&self) -> String {
+        if self.member.starts_with(|c: char| c.is_ascii_digit()) {
+            format!("_{}", self.member)
+        } else {
+            self.member.clone()
+        }
+    }
+
+    fn is_captured(&self) -> bool {
+        self.conversion == "location" || self.conversion == "backtrace"
+    }
+}
+
+/// A case of an error type, in the input of the `methods!` macro
+struct MethodVariant {
+    cfg: String,
+    name: Ident,
+    path: String,
+    fields: Vec<MethodField>,
+}
+
+/// The input of the `methods!` macro, generated by `custom_error!`:
+/// `( $crate )`, the list of the items to generate (`accessors`, `constructors`, `context`),
+/// `[ visibility ] Name [ parameters ] [ arguments ] [ where predicates ]`, and each case of the error
+/// with its `cfg` attributes, its name, the path used to match it and its fields:
+/// `[ #[cfg(...)] ] Name ( Self::Name ) { (kind conversion field: Type) }`
+struct Methods {
+    /// The path of the `custom_error` crate
+    krate: TokenStream,
+    accessors: bool,
+    constructors: bool,
+    context: bool,
+    /// The visibility of the error type, used for its context selectors
+    vis: String,
+    name: Ident,
+    params: String,
+    args: String,
+    predicates: String,
+    /// The names of the type parameters of the error
+    type_params: Vec<String>,
+    variants: Vec<MethodVariant>,
+}
+
+impl Methods {
+    fn parse(input: TokenStream) -> Result<Methods> {
+        let mut cursor = Cursor::new(input);
+        let krate = cursor.group()?.stream();
+        let methods: Vec<String> = cursor.group()?.stream().into_iter().map(|method| method.to_string()).collect();
+        let vis = cursor.group()?.stream().to_string();
+        let name = cursor.ident()?;
+        let params = cursor.group()?.stream();
+        let args = cursor.group()?.stream().to_string();
+        let predicates = cursor.group()?.stream().to_string();
+        let type_params = split_commas(params.clone().into_iter().collect())
+            .into_iter()
+            .filter_map(|param| match param.first() {
+                Some(TokenTree::Ident(ident)) if ident.to_string() != "const" => Some(ident.to_string()),
+                _ => None,
+            })
+            .collect();
+        let mut variants = Vec::new();
+        while let Some(TokenTree::Group(cfg)) = cursor.next() {
+            let variant = cursor.ident()?;
+            let path = match cursor.next() {
+                Some(TokenTree::Group(path)) => path.stream().to_string(),
+                _ => return Err(Error::new(variant.span(), "custom_error: invalid input for methods!")),
+            };
+            let mut fields = Vec::new();
+            if let Some(TokenTree::Group(group)) = cursor.peek() {
+                if group.delimiter() != Delimiter::Bracket {
+                    let group = group.clone();
+                    cursor.next();
+                    for field in group.stream() {
+                        if let TokenTree::Group(field) = field {
+                            fields.push(MethodField::parse(field)?);
+                        }
+                    }
+                }
+            }
+            variants.push(MethodVariant { cfg: cfg.stream().to_string(), name: variant, path, fields });
+        }
+        if methods.iter().any(|method| method == "accessors") && variants.iter().any(|variant| variant.path == "Self") {
+            return Err(Error::new(
+                name.span(),
+                "custom_error: the #[accessors] option can only be used on errors with several cases",
+            ));
+        }
+        Ok(Methods {
+            krate,
+            accessors: methods.iter().any(|method| method == "accessors"),
+            constructors: methods.iter().any(|method| method == "constructors"),
+            context: methods.iter().any(|method| method == "context"),
+            vis,
+            name,
+            params: params.to_string(),
+            args,
+            predicates,
+            type_params,
+            variants,
+        })
+    }
+
+    fn expand(&self) -> String {
+        let mut methods = String::new();
+        let mut selectors = String::new();
+        for variant in &self.variants {
+            if self.context {
+                selectors.push_str(&self.context_selector(variant));
+            }
+            if self.accessors {
+                methods.push_str(&self.accessors(variant));
+            }
+            if self.constructors {
+                methods.push_str(&self.constructors(variant));
+            }
+        }
+        format!(
+            "#[allow(dead_code, deprecated)] impl<{}> {}<{}> where {} {{ {} }} {}",
+            self.params, self.name, self.args, self.predicates, methods, selectors,
+        )
+    }
+
+    /// The `is_*` method of a case, and its `as_*` and `into_*` methods if it has a single field
+    fn accessors(&self, variant: &MethodVariant) -> String {
+        let snake = snake_case(&variant.name.to_string());
+        let mut methods = format!(
+            "{cfg} #[doc = \"Returns `true` if the error is a `{name}`\"]
+            pub fn is_{snake}(&self) -> bool {{
+                #[allow(unreachable_patterns)] match self {{ {path} {{ .. }} => true, _ => false }}
+            }}",
+            cfg = variant.cfg,
+            name = variant.name,
+            path = variant.path,
+            snake = snake,
+        );
+        if let [ref field] = variant.fields[..] {
+            methods.push_str(&format!(
+                "{cfg} #[doc = \"Returns the field of the error if it is a `{name}`\"]
+                pub fn as_{snake}(&self) -> {private}::Option<&{ty}> {{
+                    #[allow(unreachable_patterns)]
+                    match self {{ {path} {{ {member}: field }} => {private}::Option::Some(field), _ => {private}::Option::None }}
+                }}
+                {cfg} #[doc = \"Returns the field of the error if it is a `{name}`, or the error otherwise\"]
+                pub fn into_{snake}(self) -> {private}::Result<{ty}, Self> {{
+                    #[allow(unreachable_patterns)]
+                    match self {{ {path} {{ {member}: field }} => {private}::Result::Ok(field), error => {private}::Result::Err(error) }}
+                }}",
+                private = PRIVATE,
+                cfg = variant.cfg,
+                name = variant.name,
+                path = variant.path,
+                snake = snake,
+                member = field.member,
+                ty = to_string(&field.ty),
+            ));
+        }
+        methods
+    }
+
+    /// The constructor of a case, named after the case (or `new` for single-case errors),
+    /// and its variant that returns the error in `Err`.
+    /// Fields are converted with `Into`, except for primitive types, references and type parameters.
+    fn constructors(&self, variant: &MethodVariant) -> String {
+        let snake = if variant.path == "Self" { "new".to_string() } else { snake_case(&variant.name.to_string()) };
+        let mut params = Vec::new();
+        let mut args = Vec::new();
+        let mut values = Vec::new();
+        for field in &variant.fields {
+            let ty = to_string(&field.ty);
+            let value = match field.conversion.as_str() {
+                "location" => format!("{}::Location::caller()", PRIVATE),
+                "backtrace" => format!("{}::Backtrace::capture()", PRIVATE),
+                _ if self.is_plain(&field.ty) => {
+                    params.push(format!("{}: {}", field.param(), ty));
+                    field.param()
+                }
+                _ => {
+                    params.push(format!("{}: impl Into<{}>", field.param(), ty));
+                    format!("{}.into()", field.param())
+                }
+            };
+            if !field.is_captured() {
+                args.push(field.param());
+            }
+            values.push(format!("{}: {}", field.member, value));
+        }
+        format!(
+            "{cfg} #[doc = \"Creates a `{name}` error\"] #[track_caller]
+            pub fn {snake}({params}) -> Self {{ {path} {{ {values} }} }}
+            {cfg} #[doc = \"Returns a `{name}` error in `Err`\"] #[track_caller]
+            pub fn {snake}_err<__T>({params}) -> {private}::Result<__T, Self> {{ {private}::Err(Self::{snake}({args})) }}",
+            private = PRIVATE,
+            cfg = variant.cfg,
+            name = variant.name,
+            path = variant.path,
+            snake = snake,
+            params = params.join(", "),
+            values = values.join(", "),
+            args = args.join(", "),
+        )
+    }
+
+    /// The context selector of a case, named after the case with a `Ctx` suffix, that holds its fields
+    /// except for the source and the captured fields, and implements `IntoError` to create the error.
+    /// Fields are converted with `Into`, except for primitive types.
+    fn context_selector(&self, variant: &MethodVariant) -> String {
+        let selector = format!("{}Ctx", variant.name);
+        let named = variant.fields.iter().any(|field| !field.member.starts_with(|c: char| c.is_ascii_digit()));
+        let mut selector_params = Vec::new();
+        let mut selector_fields = Vec::new();
+        let mut bounds = Vec::new();
+        let mut values = Vec::new();
+        let mut source = format!("{}::NoSource", CRATE);
+        for field in &variant.fields {
+            let ty = to_string(&field.ty);
+            let value = match field.conversion.as_str() {
+                "location" => format!("{}::Location::caller()", PRIVATE),
+                "backtrace" => format!("{}::Backtrace::capture()", PRIVATE),
+                _ if field.kind == "source" => {
+                    source = ty;
+                    "source".to_string()
+                }
+                _ => {
+                    let member = if named { field.member.clone() } else { selector_fields.len().to_string() };
+                    if PLAIN_TYPES.contains(&ty.as_str()) {
+                        selector_fields.push((member.clone(), ty));
+                    } else {
+                        let param = format!("__T{}", selector_params.len());
+                        bounds.push(format!("{}: Into<{}>,", param, ty));
+                        selector_fields.push((member.clone(), param.clone()));
+                        selector_params.push(param);
+                    }
+                    format!("self.{}.into()", member)
+                }
+            };
+            values.push(format!("{}: {}", field.member, value));
+        }
+        let vis = &self.vis;
+        let definition = if selector_fields.is_empty() {
+            ";".to_string()
+        } else if named {
+            let fields: Vec<String> =
+                selector_fields.iter().map(|(member, ty)| format!("{} {}: {}", vis, member, ty)).collect();
+            format!("{{ {} }}", fields.join(", "))
+        } else {
+            let fields: Vec<String> = selector_fields.iter().map(|(_, ty)| format!("{} {}", vis, ty)).collect();
+            format!("({});", fields.join(", "))
+        };
+        let impl_params: Vec<&str> =
+            [self.params.as_str()].iter().cloned().chain(selector_params.iter().map(String::as_str)).filter(|param| !param.is_empty()).collect();
+        let path = if variant.path == "Self" { self.name.to_string() } else { format!("{}::{}", self.name, variant.name) };
+        format!(
+            "{cfg} #[doc = \"Context selector of `{name}` errors\"] #[derive(Debug, Clone, Copy)]
+            {vis} struct {selector}<{selector_params}> {definition}
+            {cfg} #[allow(deprecated)] impl<{impl_params}> {krate}::IntoError<{errtype}<{args}>> for {selector}<{selector_params}>
+            where {predicates} {bounds} {{
+                type Source = {source};
+                #[track_caller]
+                #[allow(unused_variables)]
+                fn into_error(self, source: Self::Source) -> {errtype}<{args}> {{ {path} {{ {values} }} }}
+            }}",
+            cfg = variant.cfg,
+            name = variant.name,
+            vis = vis,
+            selector = selector,
+            selector_params = selector_params.join(", "),
+            definition = definition,
+            impl_params = impl_params.join(", "),
+            errtype = self.name,
+            args = self.args,
+            predicates = self.predicates,
+            bounds = bounds.join(" "),
+            source = source,
+            path = path,
+            values = values.join(", "),
+            krate = CRATE,
+        )
+    }
+
+    /// Returns true if the fields of this type are passed as they are to constructors
+    fn is_plain(&self, ty: &[TokenTree]) -> bool {
+        let ty = to_string(ty);
+        ty.starts_with('&') || PLAIN_TYPES.contains(&ty.as_str()) || self.type_params.contains(&ty)
+    }
+}
@@ -1,3 +1,8 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+#![cfg_attr(feature = "nightly", feature(allow_internal_unstable))]
+#![cfg_attr(feature = "nightly", allow(internal_features))]
+#![cfg_attr(feature = "nightly", feature(error_generic_member_access))]
+
 /// Constructs a custom error type.
 ///
 /// # Examples
@@ -43,6 +48,457 @@
 /// );
 /// ```
 ///
+/// ### Tuple variants
+///
+/// Error cases can also hold unnamed fields.
+/// The error message references them by their position: `{0}`, `{1}`...
+///
+/// If a tuple case holds a single value that implements `Error`,
+/// this value is the source of the error.
+/// The conversion from its type is implemented if it is marked with `#[from]`.
+///
+/// ```
+/// use custom_error::custom_error;
+/// use std::{io, time::Duration};
+///
+/// custom_error!{ pub NetworkError
+///     Timeout(Duration)      = "timed out after {0:?}",
+///     Refused(String, u16)   = "connection to {0}:{1} refused",
+///     Io(#[from] io::Error)  = "input/output error",
+/// }
+///
+/// assert_eq!(
+///     "timed out after 3s",
+///     NetworkError::Timeout(Duration::from_secs(3)).to_string()
+/// );
+/// assert_eq!(
+///     "connection to localhost:80 refused",
+///     NetworkError::Refused("localhost".into(), 80).to_string()
+/// );
+///
+/// let err: NetworkError = io::Error::from(io::ErrorKind::BrokenPipe).into();
+/// assert_eq!("input/output error", err.to_string());
+/// ```
+///
+/// ### Single-case errors
+///
+/// If your error can only happen in one way, you can omit the name of the error case.
+/// A struct will be generated instead of an enum.
+///
+/// ```
+/// use custom_error::custom_error;
+/// use std::{error::Error, io};
+///
+/// custom_error!{ pub ParseError{line: usize} = "syntax error on line {line}" }
+/// custom_error!{ pub Timeout = "the operation timed out" }
+/// custom_error!{ pub ConfigError{source: io::Error} = "unable to read the configuration" }
+///
+/// assert_eq!("syntax error on line 3", ParseError{line: 3}.to_string());
+/// assert_eq!("the operation timed out", Timeout.to_string());
+///
+/// let err = ConfigError::from(io::Error::from(io::ErrorKind::NotFound));
+/// assert_eq!("unable to read the configuration", err.to_string());
+/// assert!(err.source().is_some());
+/// ```
+///
+/// ### Visibility
+///
+/// The error type can have any visibility (`pub`, `pub(crate)`, `pub(super)`, `pub(in path)`),
+/// and so can the fields of single-case errors.
+///
+/// ```
+/// mod parser {
+///     use custom_error::custom_error;
+///
+///     custom_error!{ pub(crate) SyntaxError{pub line: usize, pub(crate) column: usize, _token: char} =
+///         "syntax error at {line}:{column}"
+///     }
+///
+///     pub(crate) fn parse(_input: &str) -> Result<(), SyntaxError> {
+///         Err(SyntaxError{line: 3, column: 7, _token: '}'})
+///     }
+/// }
+///
+/// let err = parser::parse("{}}").unwrap_err();
+/// assert_eq!((3, 7), (err.line, err.column));
+/// ```
+///
+/// ### Format specifiers
+///
+/// Error messages are format strings, and the fields they reference can use any
+/// [format specifier](https://doc.rust-lang.org/std/fmt/#formatting-parameters):
+/// debug formatting, hexadecimal, width, precision, alignment...
+/// Fields only need to implement the formatting traits used in the message.
+///
+/// ```
+/// use custom_error::custom_error;
+/// use std::path::PathBuf;
+///
+/// custom_error!{ pub MyError
+///     Status{code: u16}                = "unexpected status {code:#06x}",
+///     Open{path: PathBuf}              = "unable to open {path:?}",
+///     Ratio{ratio: f64, data: Vec<u8>} = "compression ratio too low: {ratio:.2} for {data:x?}",
+/// }
+///
+/// assert_eq!("unexpected status 0x01f4", MyError::Status{code: 500}.to_string());
+/// assert_eq!("unable to open \"a.txt\"", MyError::Open{path: "a.txt".into()}.to_string());
+/// assert_eq!(
+///     "compression ratio too low: 0.99 for [ff]",
+///     MyError::Ratio{ratio: 0.9876, data: vec![255]}.to_string()
+/// );
+/// ```
+///
+/// ### Checking error messages
+///
+/// The placeholders in error messages are checked at compile time.
+/// A placeholder that does not reference a field of its error case is a compile error
+/// that names the error case and lists its fields:
+///
+/// ```compile_fail
+/// use custom_error::custom_error;
+///
+/// custom_error!{ pub MyError
+///     // error: unknown placeholder `{nmae}` in the message of `BadName`; available fields: name
+///     BadName{name: String} = "{nmae} is not a valid name",
+/// }
+/// ```
+///
+/// A warning is emitted for each field that is never used in the message of its error case.
+/// Sources and fields whose name starts with an underscore are not reported.
+///
+/// ```
+/// use custom_error::custom_error;
+///
+/// custom_error!{ pub MyError
+///     // warning: this field is never used in the error message
+///     Overflow{len: usize, capacity: usize} = "buffer overflow: length {len}",
+///     // no warning
+///     Underflow{len: usize, _capacity: usize} = "buffer underflow: length {len}",
+///     // warning: the field 0 of this variant is never used in the error message
+///     Invalid(usize, char) = "invalid character {1}",
+/// }
+/// ```
+///
+/// ### Fields of any type
+///
+/// The fields of an error case can have any type:
+/// references, slices, tuples, arrays, function pointers, boxed trait objects...
+///
+/// ```
+/// use custom_error::custom_error;
+/// use std::error::Error;
+///
+/// custom_error!{ pub ParseError<'a>
+///     Unexpected{input: &'a str, position: (usize, usize)} = @{
+///         format!("unexpected {:?} at {}:{}", input, position.0, position.1)
+///     },
+///     BadMagic{magic: [u8; 4]} = "invalid file header: {magic:?}",
+/// }
+///
+/// custom_error!{ pub PluginError
+///     Failed{source: Box<dyn Error + Send + Sync>} = "the plugin failed",
+/// }
+///
+/// let err = ParseError::Unexpected{input: "}", position: (3, 14)};
+/// assert_eq!("unexpected \"}\" at 3:14", err.to_string());
+///
+/// let err = PluginError::from(Box::<dyn Error + Send + Sync>::from("out of memory"));
+/// assert_eq!("out of memory", err.source().unwrap().to_string());
+/// ```
+///
+/// ### Generic errors
+///
+/// Error types can have lifetime, type and const parameters, with bounds and default values,
+/// and a `where` clause that ends with a semicolon.
+/// Const parameters can be referenced in the messages, like fields.
+/// The bounds of the parameters are inferred from the messages:
+/// `Display` is implemented when the types of the fields implement
+/// the formatting traits that the messages use for them,
+/// so a parameter that is only used in a `PhantomData` or in a field
+/// that is not displayed does not need any bound.
+/// Bounds required by custom formatting code, or by a source field of a generic type,
+/// are given with the `where` clause.
+/// The fields of generic errors cannot be used as the width or precision of a placeholder.
+///
+/// ```
+/// use custom_error::custom_error;
+/// use std::{error::Error, fmt::Debug, marker::PhantomData};
+///
+/// pub trait Backend { const NAME: &'static str; }
+///
+/// custom_error!{ pub StoreError<B: Backend, K> where K: Debug;
+///     Missing{key: K, backend: PhantomData<B>} = "missing key {key:?}",
+///     Full{_keys: Vec<K>, capacity: usize}     = "the store is full ({capacity} keys)",
+///     Closed{backend: PhantomData<B>}          = @{ format!("the {} backend is closed", B::NAME) },
+/// }
+///
+/// custom_error!{ pub Wrapped<E> where E: Error + 'static;
+///     Failed{#[source] error: E, attempts: u8} = "failed after {attempts} attempts",
+/// }
+///
+/// #[derive(Debug)]
+/// struct Disk;
+/// impl Backend for Disk { const NAME: &'static str = "disk"; }
+///
+/// let err: StoreError<Disk, u32> = StoreError::Missing{key: 7, backend: PhantomData};
+/// assert_eq!("missing key 7", err.to_string());
+/// assert_eq!("the disk backend is closed", StoreError::<Disk, u32>::Closed{backend: PhantomData}.to_string());
+///
+/// let err = Wrapped::Failed{error: std::fmt::Error, attempts: 3};
+/// assert!(err.source().is_some());
+///
+/// custom_error!{ pub BufError<T = u8, const N: usize = 64>
+///     Overflow{len: usize, last: T} = "cannot write {len} bytes in a buffer of {N} bytes (last: {last})",
+/// }
+///
+/// let err: BufError = BufError::Overflow{len: 80, last: 0xff};
+/// assert_eq!("cannot write 80 bytes in a buffer of 64 bytes (last: 255)", err.to_string());
+/// ```
+///
+/// ### Attributes and documentation
+///
+/// Attributes and doc comments can be added before the name of the error type
+/// and before each error case. They are forwarded to the generated enum and to its variants.
+/// The error type always derives `Debug`; other traits can be derived as usual.
+///
+/// ```
+/// use custom_error::custom_error;
+///
+/// custom_error!{
+///     /// Errors that can happen when parsing a number
+///     #[derive(Clone, PartialEq)]
+///     pub ParseError
+///         /// The input was empty
+///         Empty                     = "cannot parse an empty string",
+///         /// The input contains a character that is not a digit
+///         InvalidDigit{digit: char} = "invalid digit: {digit}",
+///         /// This case only exists when debug assertions are enabled
+///         #[cfg(debug_assertions)]
+///         Debug                     = "debug error"
+/// }
+///
+/// let err = ParseError::InvalidDigit{digit: 'x'};
+/// assert_eq!(err.clone(), err);
+/// assert_eq!("invalid digit: x", err.to_string());
+/// ```
+///
+/// ### Error kinds
+///
+/// The `#[kind = Name]` attribute generates a fieldless enum called `Name`,
+/// with the same cases as the error type, and a `kind()` method that returns the kind of an error.
+/// Kinds are `Copy`, `Eq` and `Hash`, and they are displayed as the name of their case,
+/// so they can be compared and counted even when the errors hold values that cannot.
+///
+/// ```
+/// use custom_error::custom_error;
+/// use std::io;
+///
+/// custom_error!{
+///     #[kind = FetchErrorKind]
+///     pub FetchError
+///         Io{source: io::Error} = "input/output error",
+///         NotFound{id: u64}     = "no item with id {id}",
+/// }
+///
+/// let err = FetchError::from(io::Error::from(io::ErrorKind::TimedOut));
+/// assert_eq!(FetchErrorKind::Io, err.kind());
+/// assert_eq!("NotFound", FetchError::NotFound{id: 3}.kind().to_string());
+/// ```
+///
+/// ### Error codes
+///
+/// Error cases can have a string code and a numeric code, given in brackets after their name.
+/// They are returned by the `code()` and `code_num()` methods,
+/// and all the cases of the error must have one.
+/// Using the same code for two cases is a compile error.
+/// If the error has a kind enum, the kind of a code can be found with `from_code`.
+///
+/// ```
+/// use custom_error::custom_error;
+///
+/// custom_error!{
+///     #[kind = ApiErrorKind]
+///     pub ApiError
+///         NotFound[code = "E0404", num = 404]{id: u64} = "no item with id {id}",
+///         Invalid[code = "E0400", num = 400](String)   = "invalid request: {0}",
+/// }
+///
+/// let err = ApiError::NotFound{id: 3};
+/// assert_eq!(("E0404", 404), (err.code(), err.code_num()));
+/// assert_eq!(Some(ApiErrorKind::Invalid), ApiErrorKind::from_code("E0400"));
+/// ```
+///
+/// ### Exit codes
+///
+/// Error cases can also have an exit code, such as the ones of `sysexits.h`,
+/// returned by the `exit_code()` method. The cases without one have the exit code 1.
+/// [`Main`] makes `main` exit with the code of its error.
+///
+/// ```
+/// use custom_error::custom_error;
+///
+/// custom_error!{CliError
+///     Usage[exit = 64]{arg: String}         = "unknown argument {arg}",
+///     Io[exit = 74]{source: std::io::Error} = "input/output error",
+///     Other                                 = "unexpected error",
+/// }
+///
+/// assert_eq!(64, CliError::Usage{arg: "-x".into()}.exit_code());
+/// assert_eq!(1, CliError::Other.exit_code());
+/// ```
+///
+/// ### Accessors
+///
+/// With the `derive` feature, the `#[accessors]` attribute generates methods for each case,
+/// named after the snake case name of the case: `is_not_found()` returns whether the error
+/// is a `NotFound`, and for cases with a single field, `as_not_found()` returns a reference
+/// to the field and `into_not_found()` returns the field, or the error if it is another case.
+///
+/// ```
+/// # #[cfg(feature = "derive")] {
+/// use custom_error::custom_error;
+/// use std::io;
+///
+/// custom_error!{
+///     #[accessors]
+///     pub FetchError
+///         Io{source: io::Error} = "input/output error",
+///         NotFound(String)      = "{0} not found",
+/// }
+///
+/// let err = FetchError::NotFound("config.toml".into());
+/// assert!(err.is_not_found() && !err.is_io());
+/// assert_eq!(Some(&"config.toml".to_string()), err.as_not_found());
+/// assert!(err.into_io().is_err());
+/// # }
+/// ```
+///
+/// ### Constructors
+///
+/// With the `derive` feature, the `#[constructors]` attribute generates a constructor for each case,
+/// named after the snake case name of the case (or `new` for single-case errors),
+/// that takes the fields of the case in order and converts them with `Into`,
+/// except fields of primitive types, references and type parameters.
+/// Locations and backtraces are captured instead of being passed.
+/// Another constructor, suffixed with `_err`, returns the error in `Err`.
+///
+/// ```
+/// # #[cfg(feature = "derive")] {
+/// use custom_error::custom_error;
+///
+/// custom_error!{
+///     #[constructors]
+///     pub SantaError
+///         BadChild{name: String, foolishness: u8} = "{name} has been bad {foolishness} times this year",
+///         TooFar                                  = "The location you indicated is too far from the north pole",
+/// }
+///
+/// assert_eq!("Thomas has been bad 108 times this year", SantaError::bad_child("Thomas", 108).to_string());
+///
+/// fn find(distance: u32) -> Result<u32, SantaError> {
+///     if distance > 1000 {
+///         return SantaError::too_far_err();
+///     }
+///     Ok(distance)
+/// }
+/// assert!(find(5000).is_err());
+/// # }
+/// ```
+///
+/// ### Context selectors
+///
+/// With the `derive` feature, the `#[context]` attribute generates a context selector for each case,
+/// named after the case with a `Ctx` suffix, that holds the fields of the case except its source
+/// and the captured locations and backtraces. Fields are converted with `Into`, except fields of primitive types.
+/// [`ResultExt::context`] creates the error from the error of a `Result` and a selector,
+/// and [`OptionExt::context`] creates an error of a case without a source when an `Option` is `None`.
+/// The selectors are defined next to the error type, with its visibility:
+/// two errors with this option in the same module cannot have cases with the same name,
+/// such as `Io`, because their selectors would both be called `IoCtx`.
+///
+/// ```
+/// # #[cfg(feature = "derive")] {
+/// use custom_error::{custom_error, OptionExt, ResultExt};
+/// use std::{fs, io, path::Path};
+///
+/// custom_error!{
+///     #[context]
+///     ConfigError
+///         ReadConfig{source: io::Error, path: String} = "unable to read {path}",
+///         MissingKey{key: String}                     = "missing key {key}",
+/// }
+///
+/// fn read_key(path: &str, key: &str) -> Result<String, ConfigError> {
+///     let config = fs::read_to_string(path).context(ReadConfigCtx { path })?;
+///     let line = config.lines().find(|line| line.starts_with(key)).context(MissingKeyCtx { key })?;
+///     Ok(line.to_string())
+/// }
+///
+/// assert_eq!("unable to read /nonexistent", read_key("/nonexistent", "name").unwrap_err().to_string());
+/// # }
+/// ```
+///
+/// ### Result alias and early returns
+///
+/// The `#[result]` attribute defines a `Result<T>` alias for results whose errors are of the type,
+/// with the same visibility, and two macros that return early with an error of one of its cases,
+/// converted with `Into` to the error type of the function:
+/// `bail!(Case{field: value})` and `ensure!(condition, Case{field: value})`,
+/// which returns the error if the condition is false.
+/// Single-case errors are named after their type: `bail!(Name{field: value})`.
+/// Like any `macro_rules!` macro, they can be used after the error type,
+/// including in the modules declared after it. They refer to the error type by its name,
+/// so it must be imported in these modules, for example with `use super::ParseError;`.
+/// Only one error type of a module can have this option, since the aliases and the macros
+/// of two of them would conflict: define the other ones in their own modules.
+/// This option cannot be used on generic errors.
+///
+/// ```
+/// #[macro_use] extern crate custom_error;
+///
+/// custom_error!{
+///     #[result]
+///     ParseError
+///         Empty               = "empty input",
+///         TooLong{len: usize} = "input of {len} bytes",
+///         Invalid(char)       = "invalid character {0:?}",
+/// }
+///
+/// fn parse(input: &str) -> Result<u32> {
+///     ensure!(!input.is_empty(), Empty);
+///     ensure!(input.len() < 10, TooLong{len: input.len()});
+///     match input.chars().find(|c| !c.is_ascii_digit()) {
+///         Some(c) => bail!(Invalid(c)),
+///         None => Ok(input.parse().unwrap()),
+///     }
+/// }
+///
+/// # fn main() {
+/// assert_eq!("invalid character 'x'", parse("1x").unwrap_err().to_string());
+/// # }
+/// ```
+///
+/// ### Listing the error cases
+///
+/// The `variant_name()` method returns the name of the case of an error,
+/// and the `VARIANTS` constant describes all the cases of the error type with a [`VariantInfo`]:
+/// their names, the names and types of their fields, and their messages.
+///
+/// ```
+/// use custom_error::custom_error;
+///
+/// custom_error!{ pub FetchError
+///     Timeout{seconds: u64} = "timed out after {seconds} seconds",
+///     Refused(String, u16)  = "connection to {0}:{1} refused",
+/// }
+///
+/// assert_eq!("Timeout", FetchError::Timeout{seconds: 3}.variant_name());
+/// let refused = &FetchError::VARIANTS[1];
+/// assert_eq!("Refused", refused.name);
+/// assert_eq!((&["0", "1"][..], &["String", "u16"][..]), (refused.field_names, refused.field_types));
+/// assert_eq!(Some("connection to {0}:{1} refused"), refused.message);
+/// ```
+///
 ///  ### Automatic conversion from other error types
 ///
 /// You can add a special field named `source` to your error types.
@@ -55,10 +511,17 @@
 ///
 /// #### limitations
 ///  * You cannot have several error cases that contain a single *source* field of the same type:
-///         `custom_error!(E A{source:X} B{source:Y})` is allowed, but
-///         `custom_error!(E A{source:X} B{source:X})` is forbidden.
+///    `custom_error!(E A{source:X} B{source:Y})` is allowed, but
+///    `custom_error!(E A{source:X} B{source:X})` is forbidden.
+///    Marking one of the fields with `#[source]` disables the conversion from its type.
+///    The same goes for tuple cases with a single value marked with `#[from]`:
+///    `custom_error!(E A(#[from] X) B(#[from] X))` is forbidden.
 ///  * If the source field is not the only one, then the automatic conversion
-///    will not be implemented.
+///    is only implemented if the other fields are given a value with `#[default = value]`,
+///    or are captured: fields of type `&'static Location<'static>` hold the
+///    [location](https://doc.rust-lang.org/std/panic/struct.Location.html)
+///    where the conversion happened, and fields of type `Backtrace` a backtrace.
+///    A source marked with `#[from]` is also converted if its other fields implement `Default`.
 ///
 /// ```
 /// use custom_error::custom_error;
@@ -81,6 +544,119 @@
 /// )
 /// ```
 ///
+/// ```
+/// use custom_error::custom_error;
+/// use std::{io, fs, panic::Location, path::PathBuf};
+///
+/// custom_error!{MyError
+///     Io{source: io::Error, #[default = None] path: Option<PathBuf>} = "unable to read {path:?}",
+///     Number{
+///         source: std::num::ParseIntError,
+///         #[default = 10] radix: u32,
+///         location: &'static Location<'static>
+///     } = "invalid base {radix} number at {location}",
+/// }
+///
+/// fn read_number(filename: &str) -> Result<u64, MyError> {
+///     Ok(fs::read_to_string(filename)?.trim().parse()?)
+/// }
+///
+/// assert_eq!("unable to read None", read_number("/i'm not a file/").unwrap_err().to_string());
+/// ```
+///
+/// ### Transparent error cases
+///
+/// An error case with a single field can be `transparent`:
+/// its message and its source are the ones of the error it wraps.
+///
+/// ```
+/// use custom_error::custom_error;
+/// use std::{error::Error, io};
+///
+/// custom_error!{ pub MyError
+///     Io{source: io::Error}          = transparent,
+///     Parse(std::num::ParseIntError) = transparent,
+///     Unknown                        = "unknown error",
+/// }
+///
+/// let err = MyError::from(io::Error::other("disk full"));
+/// assert_eq!("disk full", err.to_string());
+/// assert!(err.source().is_none());
+/// ```
+///
+/// ### Backtraces
+///
+/// A field of type `Backtrace` holds a backtrace captured when the error is converted
+/// from its source. Error types that contain backtraces have a `backtrace()` method,
+/// that returns the backtrace of the error case, if it has one.
+/// When the `nightly` feature of this crate is enabled,
+/// backtraces are also provided by
+/// [`Error::provide`](https://doc.rust-lang.org/std/error/trait.Error.html#method.provide).
+///
+/// ```
+/// use custom_error::custom_error;
+/// use std::{io, backtrace::Backtrace};
+///
+/// custom_error!{ pub MyError
+///     Internal{source: io::Error, backtrace: Backtrace} = "internal error",
+///     Unknown                                           = "unknown error",
+/// }
+///
+/// let err = MyError::from(io::Error::from(io::ErrorKind::Other));
+/// println!("{}", err.backtrace().unwrap());
+/// assert!(MyError::Unknown.backtrace().is_none());
+/// ```
+///
+/// ### Source and conversion markers
+///
+/// A field with a different name can be marked as the source of the error with `#[source]`.
+/// Marking a field with `#[from]` makes it the source of the error,
+/// and implements the conversion from its type when it is the only field of its error case.
+/// A field named `source` that is marked with `#[source]` is the source of the error,
+/// but the conversion from its type is not implemented.
+/// Markers can be used on the fields of tuple cases too.
+/// Other attributes, such as doc comments, are forwarded to the fields.
+///
+/// ```
+/// use custom_error::custom_error;
+/// use std::{error::Error, io, num::ParseIntError};
+///
+/// custom_error!{ pub ConfigError
+///     Read{#[source] cause: io::Error, path: String} = "unable to read {path}",
+///     Parse{#[from] inner: ParseIntError}            = "invalid number",
+///     Env(#[from] std::env::VarError)                = "invalid environment variable",
+/// }
+///
+/// let err = ConfigError::Read{cause: io::ErrorKind::NotFound.into(), path: "a.toml".into()};
+/// assert!(err.source().is_some());
+///
+/// let err: ConfigError = "x".parse::<u8>().unwrap_err().into();
+/// assert_eq!("invalid number", err.to_string());
+/// ```
+///
+/// ### no_std
+///
+/// Without the default `std` feature, this crate is `#![no_std]`,
+/// and the generated code only uses `core::fmt` and `core::error::Error`,
+/// which requires Rust 1.81.
+/// Messages are written directly to the formatter, so they do not need an allocator.
+/// Fields such as `String` or `Box<dyn Error>` can be used in crates that have one,
+/// and `Backtrace` fields require the `std` feature.
+///
+/// ```
+/// # extern crate core;
+/// use custom_error::custom_error;
+/// use core::{fmt, num::ParseIntError, panic::Location};
+///
+/// custom_error!{ pub FirmwareError
+///     Fmt{source: fmt::Error}                                    = "unable to format the report",
+///     Parse{source: ParseIntError, at: &'static Location<'static>} = "invalid value at {at}",
+///     Register(u8, u32)                                          = "register {0:#04x} holds {1:#x}",
+/// }
+///
+/// assert_eq!("register 0x0a holds 0xff", FirmwareError::Register(10, 255).to_string());
+/// ```
+///
 ///  ### Custom formatting function for error messages
 ///
 /// If the format string syntax is not enough to express your complex error formatting needs,
@@ -117,208 +693,2595 @@
 ///
 /// assert_eq!("The operation timed out", MyError::Io{source: TimedOut.into()}.to_string());
 /// ```
-
 #[macro_export]
 macro_rules! custom_error {
-    (pub $($tt:tt)*) => { $crate::custom_error!{ (pub) $($tt)* } };
-
+    // Generic error type
     (
-        $( ($prefix:tt) )* // `pub` marker
+        $( #[ $($attr:tt)* ] )* // Attributes of the error type (derives, documentation...) and options
+        $vis:vis // Visibility of the error type
         $errtype:ident // Name of the error type to generate
-        $( < $(
-            $type_param:tt // Optional type parameters for generic error types
-            ),*
-        > )*
-        $(
-            $field:ident // Name of an error variant
-            $( { $(
-                $attr_name:ident // Name of an attribute of the error variant
-                :
-                $($attr_type:ident)::* // type of the attribute
-                $(< $($attr_type_param:tt),* >)* // Generic (lifetime & type) parameters for the attribute's type
-            ),* } )*
-            =
-            $( @{ $($msg_fun:tt)* } )*
-            $($msg:expr)* // The human-readable error message
-         ),*
-         $(,)* // Trailing comma
+        < $($generics:tt)* // Generic parameters, where clause, and cases of the error
     ) => {
-        #[derive(Debug)]
-        $($prefix)* enum $errtype $( < $($type_param),* > )* {
-            $(
-                $field
-                $( { $( $attr_name : $($attr_type)::* $(< $($attr_type_param),* >)* ),* } )*
-            ),*
-        }
-
-        $crate::add_type_bounds! {
-        ( $($($type_param),*)* )
-        (std::fmt::Debug + std::fmt::Display)
-        { impl <} {> std::error::Error
-            for $errtype $( < $($type_param),* > )*
-        {
-            fn source(&self) -> Option<&(dyn std::error::Error + 'static)>
-            {
-                #[allow(unused_variables, unreachable_code)]
-                match self {$(
-                    $errtype::$field $( { $( $attr_name ),* } )* => {
-                        $( $(
-                            $crate::return_if_source!($attr_name, $attr_name $(<$($attr_type_param),*>)* )
-                        );* )*;
-                        None
-                    }
-                ),*}
-            }
+        $crate::parse_options!{
+            [ $( #[ $($attr)* ] )* ] [] [ (kind) (methods) (result) ]
+            ( @param [ $vis ] $errtype ) [] () [] [] $($generics)*
         }
-        }}
+    };
 
-        $crate::impl_error_conversion!{
-            ( $errtype $(< $($type_param),* >)* )
-            $([
-                $field,
-                $($(
-                    $attr_name,
-                    $attr_name,
-                    $($attr_type)::* $(< $($attr_type_param),* >)*
-                ),*),*
-            ])*
-        }
-
-        $crate::add_type_bounds! {
-        ( $($($type_param),*)* )
-        (std::string::ToString)
-        { impl <} {> std::fmt::Display
-            for $errtype $( < $($type_param),* > )*
-        {
-            fn fmt(&self, formatter: &mut std::fmt::Formatter)
-                -> std::fmt::Result
-            {
-                match self {$(
-                    $errtype::$field $( { $( $attr_name ),* } )* => {
-                        $(write!(formatter, "{}", ($($msg_fun)*) )?;)*
-                        $crate::display_message!(formatter, $($($attr_name),*),* | $($msg)*);
-                        Ok(())
-                    }
-                ),*}
-            }
+    (
+        $( #[ $($attr:tt)* ] )*
+        $vis:vis
+        $errtype:ident
+        $($variants:tt)* // Optional where clause, and cases of the error
+    ) => {
+        $crate::parse_options!{
+            [ $( #[ $($attr)* ] )* ] [] [ (kind) (methods) (result) ]
+            ( @where_clause [ $vis ] $errtype ) [] $($variants)*
+        }
+    };
+}
+
+/* This macro extracts the options of the generated code from the attributes of the error type.
+The other attributes are forwarded to the type.
+The options are stored in a list with one entry per option:
+`(kind Name)` generates a fieldless enum called `Name` with the kinds of the error,
+`(methods ...)` lists the items named after the cases to generate:
+`accessors` (`is_*`, `as_*` and `into_*` methods), `constructors` and `context` selectors,
+and `(result Result)` generates a `Result` alias and the `ensure!` and `bail!` macros. */
+#[doc(hidden)]
+#[macro_export]
+macro_rules! parse_options {
+    (
+        [ #[kind = $kind:ident] $($attrs:tt)* ] $meta:tt
+        [ (kind $($_kind:ident)?) $methods:tt $result:tt ]
+        $($rest:tt)*
+    ) => {
+        $crate::parse_options!{ [ $($attrs)* ] $meta [ (kind $kind) $methods $result ] $($rest)* }
+    };
+    (
+        [ #[accessors] $($attrs:tt)* ] $meta:tt
+        [ $kind:tt (methods $($method:ident)*) $result:tt ]
+        $($rest:tt)*
+    ) => {
+        $crate::parse_options!{ [ $($attrs)* ] $meta [ $kind (methods $($method)* accessors) $result ] $($rest)* }
+    };
+    (
+        [ #[result] $($attrs:tt)* ] $meta:tt
+        [ $kind:tt $methods:tt (result $($_result:ident)?) ]
+        $($rest:tt)*
+    ) => {
+        $crate::parse_options!{ [ $($attrs)* ] $meta [ $kind $methods (result Result) ] $($rest)* }
+    };
+    (
+        [ #[context] $($attrs:tt)* ] $meta:tt
+        [ $kind:tt (methods $($method:ident)*) $result:tt ]
+        $($rest:tt)*
+    ) => {
+        $crate::parse_options!{ [ $($attrs)* ] $meta [ $kind (methods $($method)* context) $result ] $($rest)* }
+    };
+    (
+        [ #[constructors] $($attrs:tt)* ] $meta:tt
+        [ $kind:tt (methods $($method:ident)*) $result:tt ]
+        $($rest:tt)*
+    ) => {
+        $crate::parse_options!{ [ $($attrs)* ] $meta [ $kind (methods $($method)* constructors) $result ] $($rest)* }
+    };
+    ( [ #[ $($attr:tt)* ] $($attrs:tt)* ] [ $($meta:tt)* ] $options:tt $($rest:tt)* ) => {
+        $crate::parse_options!{ [ $($attrs)* ] [ $($meta)* #[ $($attr)* ] ] $options $($rest)* }
+    };
+    ( [] $meta:tt $options:tt ( @ $state:ident $vis:tt $errtype:ident ) $($rest:tt)* ) => {
+        $crate::parse_generics!{ @ $state ( $meta $options $vis $errtype ) $($rest)* }
+    };
+}
+
+/* This macro parses the generic parameters of the error type one by one,
+and its optional where clause, which ends with a semicolon.
+The angle brackets inside a parameter are counted, so that its bounds and its default value
+can contain generic types (`T: Into<Vec<u8>> = Vec<u8>`).
+The tokens of the current parameter are collected in a list. When its default value starts,
+the tokens collected so far are kept apart, as its declaration.
+Each parameter is stored with its declaration, its default value and its name,
+which is used in the generic arguments of the impls.
+Once they are parsed, the header of the error type is built for parse_error_variants!:
+it contains the attributes, options and visibility of the type,
+the list of generic arguments, which is empty for non-generic errors, the type used in impls: `( Name [ parameters ] [ arguments ] [ where predicates ] )`,
+and the parameters with their default values, used in the definition of the type. */
+#[doc(hidden)]
+#[macro_export]
+macro_rules! parse_generics {
+    // The end of the parameter list, or of the current parameter
+    ( @param $head:tt $params:tt $decl:tt [ $($param:tt)* ] [ ] > $($rest:tt)* ) => {
+        $crate::parse_generics!{ @name $head $params $decl [ $($param)* ] > $($rest)* }
+    };
+    ( @param $head:tt $params:tt $decl:tt [ $($param:tt)* ] [ ] , $($rest:tt)* ) => {
+        $crate::parse_generics!{ @name $head $params $decl [ $($param)* ] , $($rest)* }
+    };
+    // The default value of the parameter
+    ( @param $head:tt $params:tt ( ) [ $($param:tt)* ] [ ] = $($rest:tt)* ) => {
+        $crate::parse_generics!{ @param $head $params ( $($param)* ) [] [] $($rest)* }
+    };
+    // Angle brackets inside a parameter
+    ( @param $head:tt $params:tt $decl:tt [ $($param:tt)* ] [ $_depth:tt ] >> $($rest:tt)* ) => {
+        $crate::parse_generics!{ @name $head $params $decl [ $($param)* > ] > $($rest)* }
+    };
+    (
+        @param $head:tt $params:tt $decl:tt [ $($param:tt)* ] [ $_a:tt $_b:tt $($depth:tt)* ]
+        >> $($rest:tt)*
+    ) => {
+        $crate::parse_generics!{ @param $head $params $decl [ $($param)* >> ] [ $($depth)* ] $($rest)* }
+    };
+    ( @param $head:tt $params:tt $decl:tt [ $($param:tt)* ] [ $_a:tt $($depth:tt)* ] > $($rest:tt)* ) => {
+        $crate::parse_generics!{ @param $head $params $decl [ $($param)* > ] [ $($depth)* ] $($rest)* }
+    };
+    ( @param $head:tt $params:tt $decl:tt [ $($param:tt)* ] [ $($depth:tt)* ] < $($rest:tt)* ) => {
+        $crate::parse_generics!{ @param $head $params $decl [ $($param)* < ] [ < $($depth)* ] $($rest)* }
+    };
+    ( @param $head:tt $params:tt $decl:tt [ $($param:tt)* ] [ $($depth:tt)* ] << $($rest:tt)* ) => {
+        $crate::parse_generics!{ @param $head $params $decl [ $($param)* << ] [ < < $($depth)* ] $($rest)* }
+    };
+    ( @param $head:tt $params:tt $decl:tt [ $($param:tt)* ] $depth:tt $token:tt $($rest:tt)* ) => {
+        $crate::parse_generics!{ @param $head $params $decl [ $($param)* $token ] $depth $($rest)* }
+    };
+    // Separate the declaration of the parameter from its default value
+    ( @name $head:tt $params:tt ( ) [ $($param:tt)* ] $($rest:tt)* ) => {
+        $crate::parse_generics!{ @name $head $params [ $($param)* ] [ ] $($rest)* }
+    };
+    ( @name $head:tt $params:tt ( $($decl:tt)* ) [ $($default:tt)* ] $($rest:tt)* ) => {
+        $crate::parse_generics!{ @name $head $params [ $($decl)* ] [ = $($default)* ] $($rest)* }
+    };
+    // Extract the name of the parameter
+    (
+        @name $head:tt [ $($params:tt)* ] [ $lifetime:lifetime $($bounds:tt)* ] $default:tt
+        $($rest:tt)*
+    ) => {
+        $crate::parse_generics!{
+            @next $head [ $($params)* ( [ $lifetime $($bounds)* ] $default $lifetime ) ] $($rest)*
+        }
+    };
+    (
+        @name $head:tt [ $($params:tt)* ] [ const $name:ident $($type:tt)* ] $default:tt
+        $($rest:tt)*
+    ) => {
+        $crate::parse_generics!{
+            @next $head [ $($params)* ( [ const $name $($type)* ] $default $name ) ] $($rest)*
+        }
+    };
+    (
+        @name $head:tt [ $($params:tt)* ] [ $name:ident $($bounds:tt)* ] $default:tt
+        $($rest:tt)*
+    ) => {
+        $crate::parse_generics!{
+            @next $head [ $($params)* ( [ $name $($bounds)* ] $default $name ) ] $($rest)*
+        }
+    };
+    ( @name $head:tt $params:tt [ ] [ ] $($rest:tt)* ) => { // trailing comma
+        $crate::parse_generics!{ @next $head $params $($rest)* }
+    };
+    ( @next $head:tt $params:tt , $($rest:tt)* ) => {
+        $crate::parse_generics!{ @param $head $params () [] [] $($rest)* }
+    };
+    ( @next $head:tt $params:tt > $($rest:tt)* ) => {
+        $crate::parse_generics!{ @where_clause $head $params $($rest)* }
+    };
+    // Parse the where clause
+    ( @where_clause $head:tt $params:tt where $($rest:tt)* ) => {
+        $crate::parse_generics!{ @where $head $params [] $($rest)* }
+    };
+    ( @where_clause $head:tt $params:tt $($rest:tt)* ) => {
+        $crate::parse_generics!{ @body $head $params [] $($rest)* }
+    };
+    ( @where $head:tt $params:tt [ $($predicates:tt)* ] $(,)? ; $($rest:tt)* ) => {
+        $crate::parse_generics!{ @body $head $params [ $($predicates)* , ] $($rest)* }
+    };
+    ( @where $head:tt $params:tt [ $($predicates:tt)* ] $token:tt $($rest:tt)* ) => {
+        $crate::parse_generics!{ @where $head $params [ $($predicates)* $token ] $($rest)* }
+    };
+    // Error type with a single case, defined as a struct
+    (
+        @body ( $meta:tt $options:tt $vis:tt $errtype:ident )
+        [ $( ( [ $($param:tt)* ] [ $($default:tt)* ] $arg:tt ) )* ] $predicates:tt
+        $( [ $($codes:tt)* ] )? // Codes of the error
+        $( { $($attrs:tt)* } )? // Attributes of the error
+        $( ( $($tuple_attrs:tt)* ) )? // Attributes of the error, if it is a tuple struct
+        = $($msg:tt)* // The human-readable error message
+    ) => {
+        $crate::parse_error_variants!{
+            (
+                struct $meta $options $vis $errtype [ $($arg),* ]
+                ( $errtype [ $( $($param)* ),* ] [ $($arg),* ] $predicates )
+                [ $( $($param)* $($default)* ),* ]
+            )
+            []
+            $errtype $( [ $($codes)* ] )? $( { $($attrs)* } )? $( ( $($tuple_attrs)* ) )? = $($msg)*
+        }
+    };
+    (
+        @body ( $meta:tt $options:tt $vis:tt $errtype:ident )
+        [ $( ( [ $($param:tt)* ] [ $($default:tt)* ] $arg:tt ) )* ] $predicates:tt
+        $($variants:tt)* // The error variants
+    ) => {
+        $crate::parse_error_variants!{
+            (
+                enum $meta $options $vis $errtype [ $($arg),* ]
+                ( $errtype [ $( $($param)* ),* ] [ $($arg),* ] $predicates )
+                [ $( $($param)* $($default)* ),* ]
+            )
+            []
+            $($variants)*
+        }
+    };
+}
+
+/* This macro parses the list of error variants one by one,
+and then calls impl_custom_error! with a normalized representation of each variant.
+The attributes of each variant are kept, and its `cfg` attributes are extracted,
+so that they can also be applied to the code generated for the variant.
+Each attribute of a variant is tagged with `source` if it should be returned
+by Error::source, or with `attr` otherwise.
+It is also tagged with the way it is initialized when the error is converted from another type:
+`from` if it holds the converted value, `auto` if it holds it because it is named `source`,
+`default` if it is initialized with Default::default(),
+`location` if it holds the location of the conversion, `backtrace` if it holds a backtrace
+captured during the conversion, or `{ value }`.
+An attribute is a source if it is marked with `#[source]` or `#[from]`,
+or if it is named `source` and has no marker, in which case the error can be converted from it.
+The single attribute of a tuple variant without markers is tagged with `maybe_source`:
+it is the source of the error if its type implements Error.
+The attributes of tuple variants are bound to new identifiers,
+and associated with their position in the tuple. */
+#[doc(hidden)]
+#[macro_export]
+macro_rules! parse_error_variants {
+    // All the variants have been parsed
+    ( $header:tt [ $($variants:tt)* ] $(,)* ) => {
+        $crate::impl_custom_error!{ $header $($variants)* }
+    };
+    // Parse the next variant, when it is transparent
+    (
+        $header:tt [ $($variants:tt)* ]
+        $( #[ $($attr:tt)* ] )* // Attributes of the error variant
+        $field:ident // Name of the error variant
+        $( [ $($options:tt)* ] )? // Codes of the error variant
+        $( { $($attrs:tt)* } )? // Fields of the error variant
+        $( ( $($tuple_attrs:tt)* ) )? // Fields of the error variant, if it is a tuple variant
+        = transparent
+        $(, $($rest:tt)* )?
+    ) => {
+        $crate::parse_error_variants!{
+            @options $header [ $($variants)* ]
+            ( [ $( #[ $($attr)* ] )* ] $field ( [] [] [] ) [ transparent ] ) [ $($($options)*)? ]
+            $( [] { $($attrs)* } )?
+            $( [] [0 1 2 3 4 5 6 7 8 9 10 11 12 13 14 15] ( $($tuple_attrs)* ) )?
+            $($($rest)*)?
+        }
+    };
+    // Parse the next variant
+    (
+        $header:tt [ $($variants:tt)* ]
+        $( #[ $($attr:tt)* ] )* // Attributes of the error variant
+        $field:ident // Name of the error variant
+        $( [ $($options:tt)* ] )? // Codes of the error variant
+        $( { $($attrs:tt)* } )? // Fields of the error variant
+        $( ( $($tuple_attrs:tt)* ) )? // Fields of the error variant, if it is a tuple variant
+        =
+        $( @{ $($msg_fun:tt)* } )*
+        $($msg:expr)* // The human-readable error message
+        $(, $($rest:tt)* )?
+    ) => {
+        $crate::parse_error_variants!{
+            @options $header [ $($variants)* ]
+            ( [ $( #[ $($attr)* ] )* ] $field ( [] [] [] ) [ $( @{ $($msg_fun)* } )* $($msg)* ] )
+            [ $($($options)*)? ]
+            $( [] { $($attrs)* } )?
+            $( [] [0 1 2 3 4 5 6 7 8 9 10 11 12 13 14 15] ( $($tuple_attrs)* ) )?
+            $($($rest)*)?
+        }
+    };
+    // Parse the codes of the variant
+    (
+        @options $header:tt $variants:tt ( $attr:tt $field:ident ( $_code:tt $num:tt $exit:tt ) $msg:tt )
+        [ code = $code:expr $(, $($options:tt)* )? ]
+        $($rest:tt)*
+    ) => {
+        $crate::parse_error_variants!{
+            @options $header $variants ( $attr $field ( [ $code ] $num $exit ) $msg ) [ $($($options)*)? ]
+            $($rest)*
+        }
+    };
+    (
+        @options $header:tt $variants:tt ( $attr:tt $field:ident ( $code:tt $_num:tt $exit:tt ) $msg:tt )
+        [ num = $num:expr $(, $($options:tt)* )? ]
+        $($rest:tt)*
+    ) => {
+        $crate::parse_error_variants!{
+            @options $header $variants ( $attr $field ( $code [ $num ] $exit ) $msg ) [ $($($options)*)? ]
+            $($rest)*
+        }
+    };
+    (
+        @options $header:tt $variants:tt ( $attr:tt $field:ident ( $code:tt $num:tt $_exit:tt ) $msg:tt )
+        [ exit = $exit:expr $(, $($options:tt)* )? ]
+        $($rest:tt)*
+    ) => {
+        $crate::parse_error_variants!{
+            @options $header $variants ( $attr $field ( $code $num [ $exit ] ) $msg ) [ $($($options)*)? ]
+            $($rest)*
+        }
+    };
+    ( @options $header:tt $variants:tt $variant:tt [] $($rest:tt)* ) => {
+        $crate::parse_error_variants!{ @attrs $header $variants $variant $($rest)* }
+    };
+    ( @options $header:tt $variants:tt ( $attr:tt $field:ident $($_variant:tt)* ) [ $($options:tt)* ] $($rest:tt)* ) => {
+        compile_error!(concat!(
+            "custom_error: invalid option `", stringify!($($options)*), "` for `", stringify!($field),
+            "`; expected `code = \"...\"`, `num = ...` or `exit = ...`"
+        ));
+    };
+    // Parse the attributes of the variant one by one.
+    // The `#[source]`, `#[from]` and `#[default = value]` markers of each attribute are extracted first.
+    (
+        @attrs $header:tt [ $($variants:tt)* ] $variant:tt
+        [ $($parsed:tt)* ] { $($attrs:tt)+ }
+        $($rest:tt)*
+    ) => {
+        $crate::parse_error_variants!{
+            @markers $header [ $($variants)* ] $variant
+            [ $($parsed)* ] {} (attr default) [ [] ] [ $($attrs)+ ]
+            $($rest)*
+        }
+    };
+    (
+        @markers $header:tt $variants:tt $variant:tt $parsed:tt $shape:tt
+        ( $_kind:ident $conversion:tt ) $field_attrs:tt [ #[source] $($attrs:tt)* ]
+        $($rest:tt)*
+    ) => {
+        $crate::parse_error_variants!{
+            @markers $header $variants $variant $parsed $shape
+            (source $conversion) $field_attrs [ $($attrs)* ]
+            $($rest)*
+        }
+    };
+    (
+        @markers $header:tt $variants:tt $variant:tt $parsed:tt $shape:tt
+        $_marker:tt $field_attrs:tt [ #[from] $($attrs:tt)* ]
+        $($rest:tt)*
+    ) => {
+        $crate::parse_error_variants!{
+            @markers $header $variants $variant $parsed $shape
+            (source from) $field_attrs [ $($attrs)* ]
+            $($rest)*
+        }
+    };
+    (
+        @markers $header:tt $variants:tt $variant:tt $parsed:tt $shape:tt
+        ( $kind:ident $_conversion:tt ) $field_attrs:tt [ #[default = $value:expr] $($attrs:tt)* ]
+        $($rest:tt)*
+    ) => {
+        $crate::parse_error_variants!{
+            @markers $header $variants $variant $parsed $shape
+            ($kind { $value }) $field_attrs [ $($attrs)* ]
+            $($rest)*
+        }
+    };
+    (
+        @markers $header:tt $variants:tt $variant:tt $parsed:tt $shape:tt
+        $marker:tt [ $($field_attrs:tt)* ] [ #[$field_attr:meta] $($attrs:tt)* ]
+        $($rest:tt)*
+    ) => {
+        $crate::parse_error_variants!{
+            @markers $header $variants $variant $parsed $shape
+            $marker [ $($field_attrs)* #[$field_attr] ] [ $($attrs)* ]
+            $($rest)*
+        }
+    };
+    // The visibility of the field is kept in front of its attributes
+    (
+        @markers $header:tt $variants:tt $variant:tt $parsed:tt $shape:tt
+        $marker:tt [ [] $($field_attrs:tt)* ] [ pub (crate) $($attrs:tt)* ]
+        $($rest:tt)*
+    ) => {
+        $crate::parse_error_variants!{
+            @markers $header $variants $variant $parsed $shape
+            $marker [ [ pub(crate) ] $($field_attrs)* ] [ $($attrs)* ]
+            $($rest)*
+        }
+    };
+    (
+        @markers $header:tt $variants:tt $variant:tt $parsed:tt $shape:tt
+        $marker:tt [ [] $($field_attrs:tt)* ] [ pub (self) $($attrs:tt)* ]
+        $($rest:tt)*
+    ) => {
+        $crate::parse_error_variants!{
+            @markers $header $variants $variant $parsed $shape
+            $marker [ [ pub(self) ] $($field_attrs)* ] [ $($attrs)* ]
+            $($rest)*
+        }
+    };
+    (
+        @markers $header:tt $variants:tt $variant:tt $parsed:tt $shape:tt
+        $marker:tt [ [] $($field_attrs:tt)* ] [ pub (super) $($attrs:tt)* ]
+        $($rest:tt)*
+    ) => {
+        $crate::parse_error_variants!{
+            @markers $header $variants $variant $parsed $shape
+            $marker [ [ pub(super) ] $($field_attrs)* ] [ $($attrs)* ]
+            $($rest)*
+        }
+    };
+    (
+        @markers $header:tt $variants:tt $variant:tt $parsed:tt $shape:tt
+        $marker:tt [ [] $($field_attrs:tt)* ] [ pub (in $($path:tt)*) $($attrs:tt)* ]
+        $($rest:tt)*
+    ) => {
+        $crate::parse_error_variants!{
+            @markers $header $variants $variant $parsed $shape
+            $marker [ [ pub(in $($path)*) ] $($field_attrs)* ] [ $($attrs)* ]
+            $($rest)*
+        }
+    };
+    (
+        @markers $header:tt $variants:tt $variant:tt $parsed:tt $shape:tt
+        $marker:tt [ [] $($field_attrs:tt)* ] [ pub $($attrs:tt)* ]
+        $($rest:tt)*
+    ) => {
+        $crate::parse_error_variants!{
+            @markers $header $variants $variant $parsed $shape
+            $marker [ [ pub ] $($field_attrs)* ] [ $($attrs)* ]
+            $($rest)*
+        }
+    };
+    (
+        @markers $header:tt $variants:tt $variant:tt $parsed:tt {}
+        $marker:tt $field_attrs:tt [ $attr_name:ident : $($attrs:tt)* ]
+        $($rest:tt)*
+    ) => {
+        $crate::parse_error_variants!{
+            @capture $header $variants $variant $parsed { $attr_name } $marker $field_attrs [ $($attrs)* ]
+            $($rest)*
+        }
+    };
+    (
+        @markers $header:tt $variants:tt $variant:tt $parsed:tt [ $($indices:tt)* ]
+        $marker:tt $field_attrs:tt [ $($attrs:tt)* ]
+        $($rest:tt)*
+    ) => {
+        $crate::parse_error_variants!{
+            @capture $header $variants $variant $parsed [ $($indices)* ] $marker $field_attrs [ $($attrs)* ]
+            $($rest)*
+        }
+    };
+    // Attributes without markers that hold a location or a backtrace are captured
+    // when the error is converted from another type
+    (
+        @capture $header:tt $variants:tt $variant:tt $parsed:tt $shape:tt
+        (attr default) $field_attrs:tt [ &'static Location<'static> $(, $($attrs:tt)* )? ]
+        $($rest:tt)*
+    ) => {
+        $crate::parse_error_variants!{
+            @field $header $variants $variant $parsed $shape
+            (attr location) $field_attrs [ &'static Location<'static> $(, $($attrs)* )? ]
+            $($rest)*
+        }
+    };
+    (
+        @capture $header:tt $variants:tt $variant:tt $parsed:tt $shape:tt
+        (attr default) $field_attrs:tt [ &'static std::panic::Location<'static> $(, $($attrs:tt)* )? ]
+        $($rest:tt)*
+    ) => {
+        $crate::parse_error_variants!{
+            @field $header $variants $variant $parsed $shape
+            (attr location) $field_attrs [ &'static std::panic::Location<'static> $(, $($attrs)* )? ]
+            $($rest)*
+        }
+    };
+    (
+        @capture $header:tt $variants:tt $variant:tt $parsed:tt $shape:tt
+        (attr default) $field_attrs:tt [ &'static core::panic::Location<'static> $(, $($attrs:tt)* )? ]
+        $($rest:tt)*
+    ) => {
+        $crate::parse_error_variants!{
+            @field $header $variants $variant $parsed $shape
+            (attr location) $field_attrs [ &'static core::panic::Location<'static> $(, $($attrs)* )? ]
+            $($rest)*
+        }
+    };
+    (
+        @capture $header:tt $variants:tt $variant:tt $parsed:tt $shape:tt
+        (attr default) $field_attrs:tt [ Backtrace $(, $($attrs:tt)* )? ]
+        $($rest:tt)*
+    ) => {
+        $crate::parse_error_variants!{
+            @field $header $variants $variant $parsed $shape
+            (attr backtrace) $field_attrs [ Backtrace $(, $($attrs)* )? ]
+            $($rest)*
+        }
+    };
+    (
+        @capture $header:tt $variants:tt $variant:tt $parsed:tt $shape:tt
+        (attr default) $field_attrs:tt [ std::backtrace::Backtrace $(, $($attrs:tt)* )? ]
+        $($rest:tt)*
+    ) => {
+        $crate::parse_error_variants!{
+            @field $header $variants $variant $parsed $shape
+            (attr backtrace) $field_attrs [ std::backtrace::Backtrace $(, $($attrs)* )? ]
+            $($rest)*
+        }
+    };
+    (
+        @capture $header:tt $variants:tt $variant:tt $parsed:tt $shape:tt
+        $marker:tt $field_attrs:tt [ $($attrs:tt)* ]
+        $($rest:tt)*
+    ) => {
+        $crate::parse_error_variants!{
+            @field $header $variants $variant $parsed $shape $marker $field_attrs [ $($attrs)* ]
+            $($rest)*
+        }
+    };
+    // The name of the attribute is repeated, so that it can be compared to `source`
+    (
+        @field $header:tt $variants:tt $variant:tt $parsed:tt { $attr_name:ident }
+        $marker:tt $field_attrs:tt [ $($attrs:tt)* ]
+        $($rest:tt)*
+    ) => {
+        $crate::parse_error_variants!{
+            @attr $header $variants $variant $parsed
+            $marker $field_attrs $attr_name { $attr_name : $($attrs)* }
+            $($rest)*
+        }
+    };
+    (
+        @field $header:tt $variants:tt $variant:tt [ $($parsed:tt)* ] [ $index:tt $($indices:tt)* ]
+        ( $kind:ident $conversion:tt ) $field_attrs:tt [ $attr_type:ty $(, $($attrs:tt)* )? ]
+        $($rest:tt)*
+    ) => {
+        $crate::parse_error_variants!{
+            @attrs $header $variants $variant
+            [ $($parsed)* ($kind $conversion $field_attrs tuple_attr $index : $attr_type) ]
+            [ $($indices)* ] ( $($($attrs)*)? )
+            $($rest)*
+        }
+    };
+    // In errors without type parameters, an attribute named `source` is always the source of the error
+    (
+        @attr
+        ( $kind:ident $meta:tt $options:tt $vis:tt $errtype:ident [] $selftype:tt $definition:tt )
+        [ $($variants:tt)* ] $variant:tt
+        [ $($parsed:tt)* ] (attr default) $field_attrs:tt
+        source { $source:ident : $source_type:ty $(, $($attrs:tt)* )? }
+        $($rest:tt)*
+    ) => {
+        $crate::parse_error_variants!{
+            @attrs
+            ( $kind $meta $options $vis $errtype [] $selftype $definition )
+            [ $($variants)* ] $variant
+            [ $($parsed)* (source auto $field_attrs $source : $source_type) ] { $($($attrs)*)? }
+            $($rest)*
+        }
+    };
+    // In generic errors, the source type may not be 'static if it has type parameters
+    (
+        @attr $header:tt [ $($variants:tt)* ] $variant:tt
+        [ $($parsed:tt)* ] (attr default) $field_attrs:tt
+        source { $source:ident : $($source_type:ident)::+ $(, $($attrs:tt)* )? }
+        $($rest:tt)*
+    ) => {
+        $crate::parse_error_variants!{
+            @attrs $header [ $($variants)* ] $variant
+            [ $($parsed)* (source auto $field_attrs $source : $($source_type)::+) ] { $($($attrs)*)? }
+            $($rest)*
+        }
+    };
+    (
+        @attr $header:tt [ $($variants:tt)* ] $variant:tt
+        [ $($parsed:tt)* ] (attr default) $field_attrs:tt
+        source { $source:ident : $source_type:ty $(, $($attrs:tt)* )? }
+        $($rest:tt)*
+    ) => {
+        $crate::parse_error_variants!{
+            @attrs $header [ $($variants)* ] $variant
+            [ $($parsed)* (attr auto $field_attrs $source : $source_type) ] { $($($attrs)*)? }
+            $($rest)*
+        }
+    };
+    (
+        @attr $header:tt [ $($variants:tt)* ] $variant:tt
+        [ $($parsed:tt)* ] ( $kind:ident $conversion:tt ) $field_attrs:tt
+        $_name:ident { $attr_name:ident : $attr_type:ty $(, $($attrs:tt)* )? }
+        $($rest:tt)*
+    ) => {
+        $crate::parse_error_variants!{
+            @attrs $header [ $($variants)* ] $variant
+            [ $($parsed)* ($kind $conversion $field_attrs $attr_name : $attr_type) ] { $($($attrs)*)? }
+            $($rest)*
+        }
+    };
+    (
+        @attrs $header:tt [ $($variants:tt)* ]
+        ( $attr:tt $field:ident $codes:tt $msg:tt ) [ $($parsed:tt)* ] { }
+        $($rest:tt)*
+    ) => {
+        $crate::parse_error_variants!{
+            @cfg $header [ $($variants)* ] ( $field $codes { $($parsed)* } = $msg ) $attr [] $attr
+            $($rest)*
+        }
+    };
+    (
+        @attrs $header:tt [ $($variants:tt)* ] ( $attr:tt $field:ident $codes:tt $msg:tt )
+        $parsed:tt [ ] ( $($attrs:tt)+ )
+        $($rest:tt)*
+    ) => {
+        compile_error!(concat!(
+            "custom_error: the tuple variant `", stringify!($field), "` has too many attributes"
+        ));
+    };
+    // Parse the attributes of a tuple variant one by one
+    (
+        @attrs $header:tt [ $($variants:tt)* ] $variant:tt
+        [ $($parsed:tt)* ] $indices:tt ( $($attrs:tt)+ )
+        $($rest:tt)*
+    ) => {
+        $crate::parse_error_variants!{
+            @markers $header [ $($variants)* ] $variant
+            [ $($parsed)* ] $indices (attr default) [ [] ] [ $($attrs)+ ]
+            $($rest)*
+        }
+    };
+    // In errors without type parameters, the attribute of a single-attribute tuple variant
+    // is the source of the error if it implements Error, unless it has markers
+    (
+        @attrs ( $kind:ident $meta:tt $options:tt $vis:tt $errtype:ident [] $selftype:tt $definition:tt )
+        [ $($variants:tt)* ]
+        ( $attr:tt $field:ident $codes:tt $msg:tt )
+        [ (attr default $field_attrs:tt $attr_name:ident $index:tt : $attr_type:ty) ] $indices:tt ( )
+        $($rest:tt)*
+    ) => {
+        $crate::parse_error_variants!{
+            @cfg ( $kind $meta $options $vis $errtype [] $selftype $definition ) [ $($variants)* ]
+            ( $field $codes ( (maybe_source default $field_attrs $attr_name $index : $attr_type) ) = $msg )
+            $attr [] $attr
+            $($rest)*
+        }
+    };
+    (
+        @attrs $header:tt [ $($variants:tt)* ]
+        ( $attr:tt $field:ident $codes:tt $msg:tt ) [ $($parsed:tt)* ] $indices:tt ( )
+        $($rest:tt)*
+    ) => {
+        $crate::parse_error_variants!{
+            @cfg $header [ $($variants)* ] ( $field $codes ( $($parsed)* ) = $msg ) $attr [] $attr
+            $($rest)*
+        }
+    };
+    // Variant without attributes
+    (
+        @attrs $header:tt [ $($variants:tt)* ]
+        ( $attr:tt $field:ident $codes:tt $msg:tt )
+        $($rest:tt)*
+    ) => {
+        $crate::parse_error_variants!{
+            @cfg $header [ $($variants)* ] ( $field $codes = $msg ) $attr [] $attr
+            $($rest)*
+        }
+    };
+    // Extract the `cfg` attributes of the variant
+    (
+        @cfg $header:tt [ $($variants:tt)* ] $variant:tt $attrs:tt
+        [ $($cfg:tt)* ] [ #[cfg $($cfg_args:tt)*] $($remaining:tt)* ]
+        $($rest:tt)*
+    ) => {
+        $crate::parse_error_variants!{
+            @cfg $header [ $($variants)* ] $variant $attrs
+            [ $($cfg)* #[cfg $($cfg_args)*] ] [ $($remaining)* ]
+            $($rest)*
+        }
+    };
+    (
+        @cfg $header:tt [ $($variants:tt)* ] $variant:tt $attrs:tt
+        $cfg:tt [ # $_attr:tt $($remaining:tt)* ]
+        $($rest:tt)*
+    ) => {
+        $crate::parse_error_variants!{
+            @cfg $header [ $($variants)* ] $variant $attrs $cfg [ $($remaining)* ]
+            $($rest)*
+        }
+    };
+    // Once the attributes are parsed, add the variant to the list, with the path used to match it
+    (
+        @cfg ( enum $($header:tt)* ) [ $($variants:tt)* ]
+        ( $field:ident $($variant:tt)* ) $attrs:tt $cfg:tt [ ]
+        $($rest:tt)*
+    ) => {
+        $crate::parse_error_variants!{
+            ( enum $($header)* ) [ $($variants)* { $attrs $cfg ( Self::$field ) $field $($variant)* } ]
+            $($rest)*
+        }
+    };
+    (
+        @cfg ( struct $($header:tt)* ) [ $($variants:tt)* ]
+        ( $field:ident $($variant:tt)* ) $attrs:tt $cfg:tt [ ]
+        $($rest:tt)*
+    ) => {
+        $crate::parse_error_variants!{
+            ( struct $($header)* ) [ $($variants)* { $attrs $cfg ( Self ) $field $($variant)* } ]
+            $($rest)*
+        }
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! impl_custom_error {
+    // Define the error enum
+    (
+        (
+            enum [ $( #[$meta:meta] )* ] $options:tt [ $vis:vis ] $errtype:ident $_args:tt
+            ( $_name:ident [ $($param:tt)* ] $args:tt [ $($predicate:tt)* ] )
+            [ $($definition:tt)* ]
+        )
+        $({
+            [ $( #[$attr:meta] )* ] // All the attributes of the variant
+            $cfg:tt
+            $path:tt
+            $field:ident
+            $codes:tt
+            $( { $( (
+                $attr_kind:ident $attr_conv:tt [ [ $($field_vis:tt)* ] $( #[$field_attr:meta] )* ] $attr_name:ident : $attr_type:ty
+            ) )* } )*
+            $( ( $( (
+                $tuple_kind:ident $tuple_conv:tt [ [ $($tuple_vis:tt)* ] $( #[$tuple_field_attr:meta] )* ]
+                $tuple_attr:ident $index:tt : $tuple_type:ty
+            ) )* ) )*
+            = $msg:tt
+        })*
+    ) => {
+        $( #[$meta] )*
+        #[derive(Debug)]
+        $vis enum $errtype < $($definition)* > where $($predicate)* {
+            $(
+                $( #[$attr] )*
+                $field
+                $( { $( $( #[$field_attr] )* $($field_vis)* $attr_name : $attr_type ),* } )*
+                $( ( $( $( #[$tuple_field_attr] )* $($tuple_vis)* $tuple_type ),* ) )*
+            ),*
+        }
+
+        $crate::impl_custom_error!{
+            @impls $options [ $vis ] (
+                $errtype [ $($param)* ] $args [ $($predicate)* ]
+                ( $errtype [ $($param)* ] $args [ $($predicate)* ] )
+            )
+            $({
+                $cfg $path $field $codes
+                $( { $( ( $attr_kind $attr_conv $attr_name : $attr_type ) )* } )*
+                $( ( $( ( $tuple_kind $tuple_conv $tuple_attr $index : $tuple_type ) )* ) )*
+                = $msg
+            })*
+        }
+    };
+    // Define the error struct
+    (
+        (
+            struct [ $( #[$meta:meta] )* ] $options:tt [ $vis:vis ] $errtype:ident $_args:tt
+            ( $_name:ident [ $($param:tt)* ] $args:tt [ $($predicate:tt)* ] )
+            [ $($definition:tt)* ]
+        )
+        {
+            $attrs:tt $cfg:tt $path:tt $field:ident $codes:tt
+            { $( (
+                $attr_kind:ident $attr_conv:tt [ [ $($field_vis:tt)* ] $( #[$field_attr:meta] )* ] $attr_name:ident : $attr_type:ty
+            ) )* }
+            = $msg:tt
+        }
+    ) => {
+        $( #[$meta] )*
+        #[derive(Debug)]
+        $vis struct $errtype < $($definition)* > where $($predicate)* {
+            $( $( #[$field_attr] )* $($field_vis)* $attr_name : $attr_type ),*
+        }
+
+        $crate::impl_custom_error!{
+            @impls $options [ $vis ] (
+                $errtype [ $($param)* ] $args [ $($predicate)* ]
+                ( $errtype [ $($param)* ] $args [ $($predicate)* ] )
+            )
+            { $cfg $path $field $codes { $( ( $attr_kind $attr_conv $attr_name : $attr_type ) )* } = $msg }
+        }
+    };
+    // Define the error struct, when it is a tuple struct
+    (
+        (
+            struct [ $( #[$meta:meta] )* ] $options:tt [ $vis:vis ] $errtype:ident $_args:tt
+            ( $_name:ident [ $($param:tt)* ] $args:tt [ $($predicate:tt)* ] )
+            [ $($definition:tt)* ]
+        )
+        {
+            $attrs:tt $cfg:tt $path:tt $field:ident $codes:tt
+            ( $( (
+                $tuple_kind:ident $tuple_conv:tt [ [ $($tuple_vis:tt)* ] $( #[$tuple_field_attr:meta] )* ]
+                $tuple_attr:ident $index:tt : $tuple_type:ty
+            ) )* )
+            = $msg:tt
+        }
+    ) => {
+        $( #[$meta] )*
+        #[derive(Debug)]
+        $vis struct $errtype < $($definition)* > ( $( $( #[$tuple_field_attr] )* $($tuple_vis)* $tuple_type ),* )
+        where $($predicate)*;
+
+        $crate::impl_custom_error!{
+            @impls $options [ $vis ] (
+                $errtype [ $($param)* ] $args [ $($predicate)* ]
+                ( $errtype [ $($param)* ] $args [ $($predicate)* ] )
+            )
+            { $cfg $path $field $codes ( $( ( $tuple_kind $tuple_conv $tuple_attr $index : $tuple_type ) )* ) = $msg }
+        }
+    };
+    // Define the error struct, when it has no attributes
+    (
+        (
+            struct [ $( #[$meta:meta] )* ] $options:tt [ $vis:vis ] $errtype:ident $_args:tt
+            ( $_name:ident [ $($param:tt)* ] $args:tt [ $($predicate:tt)* ] )
+            [ $($definition:tt)* ]
+        )
+        { $attrs:tt $cfg:tt $path:tt $field:ident $codes:tt = $msg:tt }
+    ) => {
+        $( #[$meta] )*
+        #[derive(Debug)]
+        $vis struct $errtype < $($definition)* > where $($predicate)*;
+
+        $crate::impl_custom_error!{
+            @impls $options [ $vis ] (
+                $errtype [ $($param)* ] $args [ $($predicate)* ]
+                ( $errtype [ $($param)* ] $args [ $($predicate)* ] )
+            )
+            { $cfg $path $field $codes = $msg }
+        }
+    };
+    // Implement Error, Display and From for the error type
+    (
+        @impls $options:tt $vis:tt (
+            $errtype:ident [ $($param:tt)* ] [ $($arg:tt),* ] [ $($predicate:tt)* ] $selftype:tt
+        )
+        $({
+            [ $( #[$cfg:meta] )* ] // The `cfg` attributes of the variant
+            ( $($path:tt)* ) // The path used to match the variant
+            $field:ident
+            ( $code:tt $num:tt $exit:tt ) // The code, the numeric code and the exit code of the variant
+            $( { $( ( $attr_kind:ident $attr_conv:tt $attr_name:ident : $attr_type:ty ) )* } )*
+            $( ( $( (
+                $tuple_kind:ident $tuple_conv:tt $tuple_attr:ident $index:tt : $tuple_type:ty
+            ) )* ) )*
+            = $msg:tt
+        })*
+    ) => {
+        #[allow(deprecated)]
+        impl < $($param)* > $crate::private::Error for $errtype < $($arg),* >
+        where
+            $($predicate)*
+            Self: $crate::private::fmt::Debug + $crate::private::fmt::Display,
+        {
+            fn source(&self) -> Option<&(dyn $crate::private::Error + 'static)>
+            {
+                #[allow(unused_variables, unreachable_code)]
+                match self {$(
+                    $( #[$cfg] )*
+                    $($path)* $( { $( $attr_name ),* } )* $( ( $( $tuple_attr ),* ) )* => {
+                        $crate::return_if_transparent!(
+                            $msg $field $( $( $attr_name )* )* $( $( $tuple_attr )* )*
+                        );
+                        $( $(
+                            $crate::return_if_source!($attr_kind, $attr_name);
+                        )* )*
+                        $( $(
+                            $crate::return_if_source!($tuple_kind, $tuple_attr);
+                        )* )*
+                        None
+                    }
+                ),*}
+            }
+
+            $crate::impl_provide!{
+                $({
+                    [ $( #[$cfg] )* ] ( $($path)* )
+                    ( $( { $( $attr_name ),* } )* $( ( $( $tuple_attr ),* ) )* )
+                    [ $( $( ( $attr_conv $attr_name ) )* )* $( $( ( $tuple_conv $tuple_attr ) )* )* ]
+                })*
+            }
+        }
+
+        $crate::impl_backtrace!{
+            $selftype
+            [ $( $( $($attr_conv)* )* $( $($tuple_conv)* )* )* ]
+            $({
+                [ $( #[$cfg] )* ] ( $($path)* )
+                ( $( { $( $attr_name ),* } )* $( ( $( $tuple_attr ),* ) )* )
+                [ $( $( ( $attr_conv $attr_name ) )* )* $( $( ( $tuple_conv $tuple_attr ) )* )* ]
+            })*
+        }
+
+        $(
+            $( #[$cfg] )*
+            $crate::impl_error_conversion!{
+                $selftype
+                [
+                    ( $($path)* )
+                    $( { $( ( $attr_conv $attr_name : $attr_type ) )* } )*
+                    $( ( $( ( $tuple_kind $tuple_conv $tuple_type ) )* ) )*
+                ]
+            }
+        )*
+
+        $crate::impl_variants!{
+            $selftype
+            $({
+                [ $( #[$cfg] )* ] ( $($path)* ) $field
+                [ $( $( $attr_name )* )* $( $( $index )* )* ]
+                [ $( $( $attr_type, )* )* $( $( $tuple_type, )* )* ]
+                $msg
+            })*
+        }
+
+        $crate::impl_kind!{
+            $options $vis $selftype
+            $( [ $( #[$cfg] )* ] ( $($path)* ) $field )*
+        }
+
+        $crate::impl_methods!{
+            $options $vis $selftype
+            $({
+                [ $( #[$cfg] )* ] ( $($path)* ) $field
+                $( { $( ( $attr_kind $attr_conv $attr_name : $attr_type ) )* } )*
+                $( ( $( ( $tuple_kind $tuple_conv $index : $tuple_type ) )* ) )*
+            })*
+        }
+
+        $crate::impl_result!{
+            $options $vis $selftype
+            $( ( $($path)* ) )*
+        }
+
+        $crate::impl_codes!{
+            code $options $selftype
+            $( [ $( #[$cfg] )* ] ( $($path)* ) $field $code )*
+        }
+
+        $crate::impl_codes!{
+            num $options $selftype
+            $( [ $( #[$cfg] )* ] ( $($path)* ) $field $num )*
+        }
+
+        $crate::impl_exit_code!{
+            $selftype
+            $( [ $( #[$cfg] )* ] ( $($path)* ) $exit )*
+        }
+
+        $crate::impl_custom_error!{
+            @display $selftype
+            $({
+                [ $( #[$cfg] )* ] ( $($path)* )
+                $( { $( $attr_name : $attr_type ),* } )*
+                $( ( $( $tuple_attr $index : $tuple_type ),* ) )*
+                = $msg
+            })*
+        }
+
+        $(
+            $( #[$cfg] )*
+            $crate::check_message!{
+                $msg $field $selftype
+                $( { $( ( $attr_kind $attr_conv $attr_name ) )* } )*
+                $( ( $( ( $tuple_kind $tuple_conv $index ) )* ) )*
+            }
+        )*
+    };
+    // Implement Display for errors without generic parameters:
+    // the fields are formatted directly
+    (
+        @display ( $errtype:ident [ ] [ ] [ $($predicate:tt)* ] )
+        $({
+            [ $( #[$cfg:meta] )* ] ( $($path:tt)* )
+            $( { $( $attr_name:ident : $attr_type:ty ),* } )*
+            $( ( $( $tuple_attr:ident $index:tt : $tuple_type:ty ),* ) )*
+            = $msg:tt
+        })*
+    ) => {
+        #[allow(deprecated)]
+        impl $crate::private::fmt::Display for $errtype where $($predicate)* {
+            fn fmt(&self, formatter: &mut $crate::private::fmt::Formatter)
+                -> $crate::private::fmt::Result
+            {
+                #[allow(unused_variables)]
+                match self {$(
+                    $( #[$cfg] )*
+                    $($path)* $( { $( $attr_name ),* } )* $( ( $( $tuple_attr ),* ) )* => {
+                        $crate::display_message!(
+                            formatter, $msg
+                            $($($attr_name),*),* $( ( $( $tuple_attr $index ),* ) )*
+                        );
+                        Ok(())
+                    }
+                ),*}
+            }
+        }
+    };
+    // Implement Display for generic errors.
+    // Each field is bound by the formatting traits that the message uses for it,
+    // and is wrapped in a GenericField that implements these traits.
+    (
+        @display ( $errtype:ident [ $($param:tt)* ] [ $($arg:tt),* ] [ $($predicate:tt)* ] )
+        $({
+            [ $( #[$cfg:meta] )* ] ( $($path:tt)* )
+            $( { $( $attr_name:ident : $attr_type:ty ),* } )*
+            $( ( $( $tuple_attr:ident $index:tt : $tuple_type:ty ),* ) )*
+            = $msg:tt
+        })*
+    ) => {
+        #[allow(deprecated)]
+        impl < $($param)* > $crate::private::fmt::Display for $errtype < $($arg),* >
+        where
+            $($predicate)*
+            $( $( $(
+                $crate::field_uses!($msg $attr_name): $crate::private::FieldFormats<$attr_type>,
+            )* )* )*
+            $( $( $(
+                $crate::field_uses!($msg $index): $crate::private::FieldFormats<$tuple_type>,
+            )* )* )*
+        {
+            fn fmt(&self, formatter: &mut $crate::private::fmt::Formatter)
+                -> $crate::private::fmt::Result
+            {
+                #[allow(unused_variables)]
+                match self {$(
+                    $( #[$cfg] )*
+                    $($path)* $( { $( $attr_name ),* } )* $( ( $( $tuple_attr ),* ) )* => {
+                        $crate::display_message!(
+                            @generic formatter, $msg
+                            $($($attr_name),*),* $( ( $( $tuple_attr $index ),* ) )*
+                        );
+                        Ok(())
+                    }
+                ),*}
+            }
+        }
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! impl_variants {
+    // Implement `variant_name()` and the `VARIANTS` constant, that describe the cases of the error
+    (
+        ( $errtype:ident [ $($param:tt)* ] [ $($arg:tt),* ] [ $($predicate:tt)* ] )
+        $({
+            [ $( #[$cfg:meta] )* ] ( $($path:tt)* ) $field:ident
+            [ $($attr_name:tt)* ] [ $($attr_type:ty,)* ] $msg:tt
+        })*
+    ) => {
+        #[allow(dead_code, deprecated)]
+        impl < $($param)* > $errtype < $($arg),* > where $($predicate)* {
+            /// The cases of this error type
+            pub const VARIANTS: &'static [$crate::VariantInfo] = &[$(
+                $( #[$cfg] )*
+                $crate::VariantInfo {
+                    name: stringify!($field),
+                    field_names: &[ $( stringify!($attr_name) ),* ],
+                    field_types: &[ $( stringify!($attr_type) ),* ],
+                    message: $crate::impl_variants!(@message $msg),
+                }
+            ),*];
+
+            /// Returns the name of the case of this error
+            pub fn variant_name(&self) -> &'static str {
+                match self {$(
+                    $( #[$cfg] )*
+                    $($path)* { .. } => stringify!($field)
+                ),*}
+            }
+        }
+    };
+    (@message [ transparent ]) => { None };
+    (@message [ @ $($_msg_fun:tt)* ]) => { None };
+    (@message [ ]) => { Some("") };
+    (@message [ $msg:expr ]) => { Some($msg) };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! impl_kind {
+    ( [ (kind) $_methods:tt $_result:tt ] $($_tt:tt)* ) => {};
+    // Define the enum of the error kinds, with a variant for each case of the error
+    (
+        [ (kind $kind:ident) $_methods:tt $_result:tt ] [ $vis:vis ]
+        ( $errtype:ident [ $($param:tt)* ] [ $($arg:tt),* ] [ $($predicate:tt)* ] )
+        $( [ $( #[$cfg:meta] )* ] ( $($path:tt)* ) $field:ident )*
+    ) => {
+        #[doc = concat!("The kind of a [`", stringify!($errtype), "`], without its fields")]
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+        $vis enum $kind {
+            $(
+                $( #[$cfg] )*
+                #[doc = concat!("The kind of `", stringify!($field), "` errors")]
+                $field
+            ),*
+        }
+
+        #[allow(deprecated)]
+        impl $crate::private::fmt::Display for $kind {
+            fn fmt(&self, formatter: &mut $crate::private::fmt::Formatter) -> $crate::private::fmt::Result {
+                formatter.write_str(match *self {$(
+                    $( #[$cfg] )*
+                    $kind::$field => stringify!($field)
+                ),*})
+            }
+        }
+
+        #[allow(dead_code, deprecated)]
+        impl < $($param)* > $errtype < $($arg),* > where $($predicate)* {
+            /// Returns the kind of this error
+            pub fn kind(&self) -> $kind {
+                match self {$(
+                    $( #[$cfg] )*
+                    $($path)* { .. } => $kind::$field
+                ),*}
+            }
+        }
+    };
+}
+
+/* This macro implements the methods named after the cases of errors with the `#[accessors]`
+or `#[constructors]` options, and the context selectors of errors with the `#[context]` option.
+Their names are built from the names of the cases, so they are generated by a procedural macro
+of the derive crate. */
+#[cfg(feature = "derive")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! impl_methods {
+    ( [ $_kind:tt (methods) $_result:tt ] $($_tt:tt)* ) => {};
+    (
+        [ $_kind:tt (methods $($method:ident)+) $_result:tt ] $vis:tt
+        ( $errtype:ident [ $($param:tt)* ] [ $($arg:tt),* ] [ $($predicate:tt)* ] )
+        $({ [ $($cfg:tt)* ] $path:tt $field:ident $($fields:tt)* })*
+    ) => {
+        $crate::private::methods!{
+            ($crate) [ $($method)+ ] $vis $errtype [ $($param)* ] [ $($arg),* ] [ $($predicate)* ]
+            $( [ $($cfg)* ] $field $path $($fields)* )*
+        }
+    };
+}
+
+#[cfg(not(feature = "derive"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! impl_methods {
+    ( [ $_kind:tt (methods) $_result:tt ] $($_tt:tt)* ) => {};
+    ( [ $_kind:tt (methods $method:ident $($_method:ident)*) $_result:tt ] $($_tt:tt)* ) => {
+        compile_error!(concat!(
+            "custom_error: the #[", stringify!($method), "] option requires the `derive` feature of custom_error"
+        ));
+    };
+}
+
+/* This macro defines the `Result` alias and the `ensure!` and `bail!` macros of errors
+with the `#[result]` option. The `$` token of the generated macros is passed as `$d`. */
+#[doc(hidden)]
+#[macro_export]
+macro_rules! impl_result {
+    ( [ $_kind:tt $_methods:tt (result) ] $($_tt:tt)* ) => {};
+    (
+        [ $_kind:tt $_methods:tt (result $result:ident) ] $vis:tt
+        ( $errtype:ident [] [] [] ) $($paths:tt)*
+    ) => {
+        $crate::impl_result!{ @define ($) $result $vis $errtype $($paths)* }
+    };
+    ( [ $_kind:tt $_methods:tt (result $_result:ident) ] $($_tt:tt)* ) => {
+        compile_error!("custom_error: the #[result] option can only be used on errors without generic parameters");
+    };
+    ( @define ($d:tt) $result:ident [ $vis:vis ] $errtype:ident $($paths:tt)* ) => {
+        #[doc = concat!("A `Result` whose errors are `", stringify!($errtype), "`")]
+        #[allow(dead_code)]
+        $vis type $result<T> = $crate::private::Result<T, $errtype>;
+
+        $crate::impl_result!{ @macros ($d) $errtype $($paths)* }
+    };
+    // Single-case errors are named after their type: `bail!(Name{field: value})`
+    ( @macros ($d:tt) $errtype:ident (Self) ) => {
+        /// Returns early with an error, converted with `Into`
+        #[allow(unused_macros)]
+        macro_rules! bail {
+            ($d ($d error:tt)+) => {
+                return $crate::private::Err($d ($d error)+.into())
+            };
+        }
+
+        /// Returns early with an error, converted with `Into`, if the condition is false
+        #[allow(unused_macros)]
+        macro_rules! ensure {
+            ($d condition:expr, $d ($d error:tt)+) => {
+                if !$d condition {
+                    return $crate::private::Err($d ($d error)+.into())
+                }
+            };
+        }
+    };
+    ( @macros ($d:tt) $errtype:ident $($_paths:tt)* ) => {
+        /// Returns early with an error of the given case (`bail!(Case{field: value})`), converted with `Into`
+        #[allow(unused_macros)]
+        macro_rules! bail {
+            ($d case:ident $d ($d fields:tt)*) => {
+                return $crate::private::Err($errtype::$d case $d ($d fields)*.into())
+            };
+        }
+
+        /// Returns early with an error of the given case, converted with `Into`, if the condition is false
+        #[allow(unused_macros)]
+        macro_rules! ensure {
+            ($d condition:expr, $d case:ident $d ($d fields:tt)*) => {
+                if !$d condition {
+                    return $crate::private::Err($errtype::$d case $d ($d fields)*.into())
+                }
+            };
+        }
+    };
+}
+
+/* This macro implements the `exit_code()` method and the `Exit` trait of errors.
+The cases without an exit code exit with 1. */
+#[doc(hidden)]
+#[macro_export]
+macro_rules! impl_exit_code {
+    (
+        ( $errtype:ident [ $($param:tt)* ] [ $($arg:tt),* ] [ $($predicate:tt)* ] )
+        $( [ $( #[$cfg:meta] )* ] ( $($path:tt)* ) [ $($exit:expr)? ] )*
+    ) => {
+        #[allow(dead_code, deprecated)]
+        impl < $($param)* > $errtype < $($arg),* > where $($predicate)* {
+            /// Returns the exit code of the process when it fails with this error
+            pub fn exit_code(&self) -> i32 {
+                match self {$(
+                    $( #[$cfg] )*
+                    $($path)* { .. } => $crate::impl_exit_code!(@code $($exit)?)
+                ),*}
+            }
+        }
+
+        #[allow(deprecated)]
+        impl < $($param)* > $crate::Exit for $errtype < $($arg),* > where $($predicate)* {
+            fn exit_code(&self) -> i32 {
+                Self::exit_code(self)
+            }
+        }
+    };
+    (@code) => { 1 };
+    (@code $exit:expr) => { $exit };
+}
+
+/* This macro implements the `code()` and `code_num()` methods of errors whose cases have codes,
+and checks that the codes are unique at compile time.
+The kind enum of errors with string codes can be created from a code. */
+#[doc(hidden)]
+#[macro_export]
+macro_rules! impl_codes {
+    // None of the cases have a code
+    ( $_option:ident $_options:tt $_selftype:tt $( $_cfg:tt $_path:tt $_field:ident [] )* ) => {};
+    (
+        code $options:tt ( $errtype:ident [ $($param:tt)* ] [ $($arg:tt),* ] [ $($predicate:tt)* ] )
+        $( [ $( #[$cfg:meta] )* ] ( $($path:tt)* ) $field:ident [ $code:expr ] )*
+    ) => {
+        #[allow(dead_code, deprecated)]
+        impl < $($param)* > $errtype < $($arg),* > where $($predicate)* {
+            /// Returns the code of this error
+            pub fn code(&self) -> &'static str {
+                match self {$(
+                    $( #[$cfg] )*
+                    $($path)* { .. } => $code
+                ),*}
+            }
+        }
+
+        #[allow(deprecated)]
+        const _: () = $crate::private::check_codes(
+            stringify!($errtype), &[ $( $( #[$cfg] )* (stringify!($field), $code) ),* ]
+        );
+
+        $crate::impl_codes!{ @from_code $options $( [ $( #[$cfg] )* ] $field [ $code ] )* }
+    };
+    (
+        num $_options:tt ( $errtype:ident [ $($param:tt)* ] [ $($arg:tt),* ] [ $($predicate:tt)* ] )
+        $( [ $( #[$cfg:meta] )* ] ( $($path:tt)* ) $field:ident [ $num:expr ] )*
+    ) => {
+        #[allow(dead_code, deprecated)]
+        impl < $($param)* > $errtype < $($arg),* > where $($predicate)* {
+            /// Returns the numeric code of this error
+            pub fn code_num(&self) -> u32 {
+                match self {$(
+                    $( #[$cfg] )*
+                    $($path)* { .. } => $num
+                ),*}
+            }
+        }
+
+        #[allow(deprecated)]
+        const _: () = $crate::private::check_code_nums(
+            stringify!($errtype), &[ $( $( #[$cfg] )* (stringify!($field), $num) ),* ]
+        );
+    };
+    // Some of the cases have a code, and others do not
+    ( $option:ident $_options:tt $_selftype:tt $( $_cfg:tt $_path:tt $field:ident [ $($code:expr)? ] )* ) => {
+        $( $crate::impl_codes!{ @missing $option $field [ $($code)? ] } )*
+    };
+    ( @missing $option:ident $field:ident [] ) => {
+        compile_error!(concat!(
+            "custom_error: the case `", stringify!($field), "` has no `", stringify!($option),
+            "`, but other cases of the error have one"
+        ));
+    };
+    ( @missing $option:ident $field:ident [ $_code:expr ] ) => {};
+    ( @from_code [ (kind) $_methods:tt $_result:tt ] $($_variant:tt)* ) => {};
+    ( @from_code [ (kind $kind:ident) $_methods:tt $_result:tt ] $( [ $( #[$cfg:meta] )* ] $field:ident [ $code:expr ] )* ) => {
+        #[allow(dead_code)]
+        impl $kind {
+            /// Returns the kind of the errors with the given code
+            pub fn from_code(code: &str) -> Option<Self> {
+                $(
+                    $( #[$cfg] )*
+                    {
+                        if code == $code {
+                            return Some($kind::$field);
+                        }
+                    }
+                )*
+                None
+            }
         }
-        }}
     };
 }
 
 #[doc(hidden)]
 #[macro_export]
 macro_rules! return_if_source {
-    // Return the source if the attribute is called 'source'
-    (source, $attr_name:ident) => { {return Some($attr_name)} };
-    // If the attribute has a different name or has type parameters, return nothing
-    ($_attr_name:ident, $_repeat:ident $(<$($_type:tt),*>)* ) => { };
+    // Return the source if the attribute is tagged as 'source'
+    (source, $attr_name:ident) => { {
+        use $crate::private::AsDynError;
+        return Some($attr_name.as_dyn_error())
+    } };
+    // Return the attribute if its type implements Error
+    (maybe_source, $attr_name:ident) => { {
+        #[allow(unused_imports)]
+        use $crate::private::{IsSource, DerefSource, NotSource};
+        if let Some(source) = (&&$crate::private::SourceField($attr_name)).as_source() {
+            return Some(source)
+        }
+    } };
+    // If the attribute is not tagged as 'source', return nothing
+    (attr, $_attr_name:ident) => { };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! return_if_transparent {
+    // Return the source of the single attribute of transparent variants
+    ( [ transparent ] $_field:ident $attr_name:ident ) => { {
+        use $crate::private::AsDynError;
+        return $attr_name.as_dyn_error().source()
+    } };
+    ( [ transparent ] $field:ident $($_attr_name:ident)* ) => {
+        compile_error!(concat!(
+            "custom_error: the transparent variant `", stringify!($field), "` must have a single attribute"
+        ));
+    };
+    ( $_msg:tt $($_attr:tt)* ) => { };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! impl_error_conversion {
+    // implement From<Source> only when a single attribute is tagged with 'from' or 'auto'.
+    // The values of the other attributes are computed one by one,
+    // with a bound on the types of the attributes that are initialized with their default value.
+    // The bounds of attributes tagged with 'auto' start with `auto`: they are only converted
+    // when the other attributes are not initialized with their default value.
+    ( $selftype:tt [ $path:tt { $($attrs:tt)* } ] ) => {
+        $crate::impl_error_conversion!{ @named $selftype $path source [] [] none { $($attrs)* } }
+    };
+    ( $selftype:tt [ $path:tt ( $($attrs:tt)* ) ] ) => {
+        $crate::impl_error_conversion!{ @tuple $selftype $path source [] [] none ( $($attrs)* ) }
+    };
+    ( $_selftype:tt [ $_path:tt ] ) => {}; // If the variant has no attributes, do nothing
+    (
+        @named $selftype:tt $path:tt $source:ident [ $($values:tt)* ] $bounds:tt none
+        { ( from $attr_name:ident : $attr_type:ty ) $($attrs:tt)* }
+    ) => {
+        $crate::impl_error_conversion!{
+            @named $selftype $path $source [ $($values)* $attr_name: $source, ] $bounds ( $attr_type )
+            { $($attrs)* }
+        }
+    };
+    (
+        @named $selftype:tt $path:tt $source:ident [ $($values:tt)* ] [ $($bounds:tt)* ] none
+        { ( auto $attr_name:ident : $attr_type:ty ) $($attrs:tt)* }
+    ) => {
+        $crate::impl_error_conversion!{
+            @named $selftype $path $source [ $($values)* $attr_name: $source, ] [ auto $($bounds)* ]
+            ( $attr_type ) { $($attrs)* }
+        }
+    };
+    (
+        @named $selftype:tt $path:tt $source:ident $values:tt $bounds:tt $source_type:tt
+        { ( from $($_attr:tt)* ) $($_attrs:tt)* }
+    ) => {}; // If several attributes are tagged with 'from', do nothing
+    (
+        @named $selftype:tt $path:tt $source:ident $values:tt $bounds:tt $source_type:tt
+        { ( auto $($_attr:tt)* ) $($_attrs:tt)* }
+    ) => {};
+    (
+        @named $selftype:tt $path:tt $source:ident [ $($values:tt)* ] [ $($bounds:tt)* ] $source_type:tt
+        { ( default $attr_name:ident : $attr_type:ty ) $($attrs:tt)* }
+    ) => {
+        $crate::impl_error_conversion!{
+            @named $selftype $path $source
+            [ $($values)* $attr_name: Default::default(), ]
+            [ $($bounds)* for<'source> $attr_type: Default, ]
+            $source_type { $($attrs)* }
+        }
+    };
+    (
+        @named $selftype:tt $path:tt $source:ident [ $($values:tt)* ] $bounds:tt $source_type:tt
+        { ( location $attr_name:ident : $attr_type:ty ) $($attrs:tt)* }
+    ) => {
+        $crate::impl_error_conversion!{
+            @named $selftype $path $source
+            [ $($values)* $attr_name: $crate::private::Location::caller(), ]
+            $bounds $source_type { $($attrs)* }
+        }
+    };
+    (
+        @named $selftype:tt $path:tt $source:ident [ $($values:tt)* ] $bounds:tt $source_type:tt
+        { ( backtrace $attr_name:ident : $attr_type:ty ) $($attrs:tt)* }
+    ) => {
+        $crate::impl_error_conversion!{
+            @named $selftype $path $source
+            [ $($values)* $attr_name: $crate::private::Backtrace::capture(), ]
+            $bounds $source_type { $($attrs)* }
+        }
+    };
+    (
+        @named $selftype:tt $path:tt $source:ident [ $($values:tt)* ] $bounds:tt $source_type:tt
+        { ( { $value:expr } $attr_name:ident : $attr_type:ty ) $($attrs:tt)* }
+    ) => {
+        $crate::impl_error_conversion!{
+            @named $selftype $path $source
+            [ $($values)* $attr_name: $value, ]
+            $bounds $source_type { $($attrs)* }
+        }
+    };
+    (
+        @named $selftype:tt $path:tt $source:ident $values:tt [ auto $($_bound:tt)+ ]
+        $source_type:tt { }
+    ) => {}; // If an attribute named `source` is not the only one initialized with its default value
+    (
+        @named $selftype:tt $path:tt $source:ident $values:tt [ auto ] $source_type:tt { }
+    ) => {
+        $crate::impl_error_conversion!{ @named $selftype $path $source $values [] $source_type { } }
+    };
+    (
+        @named $selftype:tt ( $($path:tt)* ) $source:ident [ $($values:tt)* ] $bounds:tt
+        ( $source_type:ty ) { }
+    ) => {
+        $crate::impl_error_conversion!{
+            @impl $selftype $source $source_type $bounds { $($path)* { $($values)* } }
+        }
+    };
+    (
+        @tuple $selftype:tt $path:tt $source:ident [ $($values:tt)* ] $bounds:tt none
+        ( ( $_kind:ident from $attr_type:ty ) $($attrs:tt)* )
+    ) => {
+        $crate::impl_error_conversion!{
+            @tuple $selftype $path $source [ $($values)* $source, ] $bounds ( $attr_type )
+            ( $($attrs)* )
+        }
+    };
+    (
+        @tuple $selftype:tt $path:tt $source:ident $values:tt $bounds:tt $source_type:tt
+        ( ( $_kind:ident from $($_attr:tt)* ) $($_attrs:tt)* )
+    ) => {}; // If several attributes are tagged with 'from', do nothing
+    (
+        @tuple $selftype:tt $path:tt $source:ident [ $($values:tt)* ] [ $($bounds:tt)* ] $source_type:tt
+        ( ( $_kind:ident default $attr_type:ty ) $($attrs:tt)* )
+    ) => {
+        $crate::impl_error_conversion!{
+            @tuple $selftype $path $source
+            [ $($values)* Default::default(), ]
+            [ $($bounds)* for<'source> $attr_type: Default, ]
+            $source_type ( $($attrs)* )
+        }
+    };
+    (
+        @tuple $selftype:tt $path:tt $source:ident [ $($values:tt)* ] $bounds:tt $source_type:tt
+        ( ( $_kind:ident location $attr_type:ty ) $($attrs:tt)* )
+    ) => {
+        $crate::impl_error_conversion!{
+            @tuple $selftype $path $source
+            [ $($values)* $crate::private::Location::caller(), ]
+            $bounds $source_type ( $($attrs)* )
+        }
+    };
+    (
+        @tuple $selftype:tt $path:tt $source:ident [ $($values:tt)* ] $bounds:tt $source_type:tt
+        ( ( $_kind:ident backtrace $attr_type:ty ) $($attrs:tt)* )
+    ) => {
+        $crate::impl_error_conversion!{
+            @tuple $selftype $path $source
+            [ $($values)* $crate::private::Backtrace::capture(), ]
+            $bounds $source_type ( $($attrs)* )
+        }
+    };
+    (
+        @tuple $selftype:tt $path:tt $source:ident [ $($values:tt)* ] $bounds:tt $source_type:tt
+        ( ( $_kind:ident { $value:expr } $attr_type:ty ) $($attrs:tt)* )
+    ) => {
+        $crate::impl_error_conversion!{
+            @tuple $selftype $path $source
+            [ $($values)* $value, ]
+            $bounds $source_type ( $($attrs)* )
+        }
+    };
+    (
+        @tuple $selftype:tt ( $($path:tt)* ) $source:ident [ $($values:tt)* ] $bounds:tt
+        ( $source_type:ty ) ( )
+    ) => {
+        $crate::impl_error_conversion!{
+            @impl $selftype $source $source_type $bounds { $($path)* ( $($values)* ) }
+        }
+    };
+    (
+        @impl ( $errtype:ident [ $($param:tt)* ] [ $($arg:tt),* ] [ $($predicate:tt)* ] )
+        $source:ident $source_type:ty [ $($bounds:tt)* ] { $($value:tt)* }
+    ) => {
+        #[allow(deprecated)]
+        impl < $($param)* >
+            From<$source_type>
+        for $errtype < $($arg),* >
+        where $($predicate)* $($bounds)* {
+            #[track_caller]
+            fn from($source: $source_type) -> Self {
+                $($value)*
+            }
+        }
+    };
+    // If no attribute is tagged with 'from', do nothing
+    ( @named $selftype:tt $path:tt $source:ident $values:tt $bounds:tt none { } ) => {};
+    ( @tuple $selftype:tt $path:tt $source:ident $values:tt $bounds:tt none ( ) ) => {};
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! display_message {
+    // In generic errors, the fields are wrapped in a GenericField before the message is written,
+    // except in messages generated by custom code
+    (@generic $formatter:expr, [ @ $($msg_fun:tt)* ] $($attrs:tt)*) => {
+        $crate::display_message!($formatter, [ @ $($msg_fun)* ] $($attrs)*);
+    };
+    (@generic $formatter:expr, $msg:tt ( $( $attr:ident $index:tt ),* )) => {
+        $( let $attr = &$crate::private::GenericField::<_, $crate::field_uses!($msg $index)>::new($attr); )*
+        $crate::display_message!($formatter, $msg ( $( $attr $index ),* ));
+    };
+    (@generic $formatter:expr, $msg:tt $($attr:ident),*) => {
+        $( let $attr = $crate::private::GenericField::<_, $crate::field_uses!($msg $attr)>::new($attr); )*
+        $crate::display_message!($formatter, $msg $($attr),*);
+    };
+    // Transparent variants are displayed like their single attribute
+    ($formatter:expr, [ transparent ] ( $attr:ident $index:tt )) => {
+        $crate::private::fmt::Display::fmt($attr, $formatter)?;
+    };
+    ($formatter:expr, [ transparent ] $attr:ident) => {
+        $crate::private::fmt::Display::fmt($attr, $formatter)?;
+    };
+    ($formatter:expr, [ transparent ] $($_attrs:tt)*) => {}; // Reported by return_if_transparent!
+    // Messages generated by custom code
+    ($formatter:expr, [ @{ $($msg_fun:tt)* } ] $($_attrs:tt)*) => {
+        write!($formatter, "{}", ($($msg_fun)*) )?;
+    };
+    ($formatter:expr, [ ] $($_attrs:tt)*) => {};
+    // The attributes of tuple variants are referenced by their position in the message.
+    // They are all referenced once more with the `Pointer` trait, that does not write
+    // anything for TupleField, to avoid the "argument never used" error.
+    ($formatter:expr, [ $msg:expr ] ( $( $attr:ident $index:tt ),* )) => {
+        write!(
+            $formatter,
+            concat!($msg $(, "{", $index, ":p}" )*)
+            $( , $crate::private::TupleField($attr) )*
+        )?;
+    };
+    // The named attributes are bound by reference in the match on the error,
+    // and the message references them directly by their name
+    ($formatter:expr, [ $msg:expr ] $($attr:ident),*) => {
+        write!($formatter, $msg)?;
+    };
 }
 
+/* This macro gives the formatting traits that the message of a generic error uses for a field,
+as a `Uses` type whose parameters are computed at compile time.
+Transparent variants display their single field. */
 #[doc(hidden)]
 #[macro_export]
-macro_rules! impl_error_conversion {
-    ( ( $($prefix:tt)* ) [ $($field_data:tt)* ] $($rest:tt)* ) => {
-        $crate::impl_error_conversion!{$($prefix)*, $($field_data)*}
-        $crate::impl_error_conversion!{ ($($prefix)*) $($rest)*}
+macro_rules! field_uses {
+    ( [ @ $_msg_fun:tt ] $_field:tt ) => { $crate::field_uses!(@none) };
+    ( [ transparent ] $_field:tt ) => {
+        $crate::private::Uses<true, false, false, false, false, false, false, false>
+    };
+    ( [ ] $_field:tt ) => { $crate::field_uses!(@none) };
+    ( [ $msg:expr ] $field:tt ) => {
+        $crate::private::Uses<
+            { $crate::private::uses_field_as($msg, stringify!($field), $crate::private::Format::Display) },
+            { $crate::private::uses_field_as($msg, stringify!($field), $crate::private::Format::Debug) },
+            { $crate::private::uses_field_as($msg, stringify!($field), $crate::private::Format::Octal) },
+            { $crate::private::uses_field_as($msg, stringify!($field), $crate::private::Format::LowerHex) },
+            { $crate::private::uses_field_as($msg, stringify!($field), $crate::private::Format::UpperHex) },
+            { $crate::private::uses_field_as($msg, stringify!($field), $crate::private::Format::Binary) },
+            { $crate::private::uses_field_as($msg, stringify!($field), $crate::private::Format::LowerExp) },
+            { $crate::private::uses_field_as($msg, stringify!($field), $crate::private::Format::UpperExp) },
+        >
+    };
+    (@none) => { $crate::private::Uses<false, false, false, false, false, false, false, false> };
+}
+
+/* This macro implements a `backtrace` method on the error type,
+if one of its attributes is tagged with `backtrace`.
+It returns the first backtrace attribute of the variant. */
+#[doc(hidden)]
+#[macro_export]
+macro_rules! impl_backtrace {
+    (
+        ( $errtype:ident [ $($param:tt)* ] [ $($arg:tt),* ] [ $($predicate:tt)* ] )
+        [ backtrace $($_conversions:tt)* ]
+        $({
+            [ $( #[$cfg:meta] )* ] ( $($path:tt)* ) ( $($attrs:tt)* ) [ $($conversions:tt)* ]
+        })*
+    ) => {
+        #[allow(deprecated, dead_code)]
+        impl < $($param)* > $errtype < $($arg),* > where $($predicate)* {
+            /// Returns the backtrace captured when this error was created, if any
+            pub fn backtrace(&self) -> Option<&$crate::private::Backtrace> {
+                #[allow(unused_variables)]
+                match self {$(
+                    $( #[$cfg] )*
+                    $($path)* $($attrs)* => $crate::impl_backtrace!(@field $($conversions)*),
+                )*}
+            }
+        }
+    };
+    ( $selftype:tt [ $_conversion:tt $($conversions:tt)* ] $($variants:tt)* ) => {
+        $crate::impl_backtrace!{ $selftype [ $($conversions)* ] $($variants)* }
+    };
+    ( $selftype:tt [ ] $($variants:tt)* ) => {};
+    (@field (backtrace $attr_name:ident) $($_attrs:tt)* ) => { Some($attr_name) };
+    (@field $_attr:tt $($attrs:tt)* ) => { $crate::impl_backtrace!(@field $($attrs)*) };
+    (@field) => { None };
+}
+
+/* This macro implements Error::provide, when the `nightly` feature is enabled.
+It provides the backtrace attributes of the error. */
+#[cfg(feature = "nightly")]
+#[doc(hidden)]
+#[macro_export]
+#[allow_internal_unstable(error_generic_member_access)]
+macro_rules! impl_provide {
+    ($({
+        [ $( #[$cfg:meta] )* ] ( $($path:tt)* ) ( $($attrs:tt)* ) [ $( ( $conversion:tt $attr_name:ident ) )* ]
+    })*) => {
+        #[allow(unused_variables)]
+        fn provide<'request>(&'request self, request: &mut $crate::private::Request<'request>) {
+            match self {$(
+                $( #[$cfg] )*
+                $($path)* $($attrs)* => {
+                    $( $crate::impl_provide!(@field request $conversion $attr_name); )*
+                }
+            )*}
+        }
+    };
+    (@field $request:ident backtrace $attr_name:ident) => {
+        $request.provide_ref::<$crate::private::Backtrace>($attr_name)
+    };
+    (@field $request:ident $_conversion:tt $_attr_name:ident) => {};
+}
+
+#[cfg(not(feature = "nightly"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! impl_provide {
+    ( $($_variants:tt)* ) => {};
+}
+
+/* This macro checks at compile time that the placeholders in the message of a variant
+reference its attributes, and emits a warning for each attribute that is not referenced.
+The warnings are deprecation warnings, emitted by calling a deprecated method named like the
+attribute (or like the variant, for tuple variants), so that they point to the user's code.
+Deprecation notes must be literals, so the notes of tuple variants, that name the position of
+the attribute, are listed for each position.
+Sources, locations, backtraces and attributes whose name starts with an underscore
+are never reported as unused. */
+#[doc(hidden)]
+#[macro_export]
+macro_rules! check_message {
+    // Messages generated by custom code cannot be checked
+    ( [ @ $_msg_fun:tt ] $($_variant:tt)* ) => {};
+    ( [ transparent ] $($_variant:tt)* ) => {};
+    ( [ ] $($_variant:tt)* ) => {};
+    (
+        [ $msg:expr ] $field:ident $selftype:tt
+        { $( ( $attr_kind:ident $attr_conv:tt $attr_name:ident ) )* }
+    ) => {
+        const _: () = {
+            struct Fields<const USED: bool>;
+            #[allow(dead_code, non_snake_case)]
+            impl Fields<false> {$(
+                #[deprecated(note = "custom_error: this field is never used in the error message")]
+                const fn $attr_name(self) {}
+            )*}
+            #[allow(dead_code, non_snake_case)]
+            impl Fields<true> {$(
+                const fn $attr_name(self) {}
+            )*}
+            $crate::private::check_message(
+                stringify!($field), $msg,
+                &[ $( stringify!($attr_name) ),* ], &$crate::check_message!(@params $selftype)
+            );
+            $( Fields::<{ $crate::check_message!(@used $attr_kind $attr_conv $attr_name $msg) }>.$attr_name(); )*
+        };
     };
-    ( ( $($prefix:tt)* ) ) => {};
-    // implement From<Source> only when there is a single attribute and it is named 'source'
     (
-        $errtype:ident $( < $($type_param:tt),* > )*,
-        $field:ident,
-        source,
-        $source:ident,
-        $($source_type:ident)::* $( < $($source_type_param:tt),* > )*
+        [ $msg:expr ] $field:ident $selftype:tt
+        ( $( ( $tuple_kind:ident $tuple_conv:tt $index:tt ) )* )
     ) => {
-        impl $( < $($type_param),* > )*
-            From<$($source_type)::* $( < $($source_type_param),* > )*>
-        for $errtype $( < $($type_param),* > )* {
-            fn from(source: $($source_type)::* $( < $($source_type_param),* > )*) -> Self {
-                $errtype::$field { source }
+        const _: () = {
+            struct Fields<const USED: bool, const INDEX: usize>;
+            $( $crate::check_message!(@unused $field $index); )*
+            #[allow(dead_code, non_snake_case)]
+            impl<const INDEX: usize> Fields<true, INDEX> {
+                const fn $field(self) {}
+            }
+            $crate::private::check_message(
+                stringify!($field), $msg,
+                &[ $( stringify!($index) ),* ], &$crate::check_message!(@params $selftype)
+            );
+            $( Fields::<{ $crate::check_message!(@used $tuple_kind $tuple_conv $index $msg) }, $index>.$field(); )*
+        };
+    };
+    ( [ $msg:expr ] $field:ident $selftype:tt ) => {
+        const _: () = $crate::private::check_message(
+            stringify!($field), $msg, &[], &$crate::check_message!(@params $selftype)
+        );
+    };
+    // The unused attributes of tuple variants are reported with their position
+    (@unused $field:ident 0) => { $crate::check_message!(@deprecated $field 0 "custom_error: the field 0 of this variant is never used in the error message"); };
+    (@unused $field:ident 1) => { $crate::check_message!(@deprecated $field 1 "custom_error: the field 1 of this variant is never used in the error message"); };
+    (@unused $field:ident 2) => { $crate::check_message!(@deprecated $field 2 "custom_error: the field 2 of this variant is never used in the error message"); };
+    (@unused $field:ident 3) => { $crate::check_message!(@deprecated $field 3 "custom_error: the field 3 of this variant is never used in the error message"); };
+    (@unused $field:ident 4) => { $crate::check_message!(@deprecated $field 4 "custom_error: the field 4 of this variant is never used in the error message"); };
+    (@unused $field:ident 5) => { $crate::check_message!(@deprecated $field 5 "custom_error: the field 5 of this variant is never used in the error message"); };
+    (@unused $field:ident 6) => { $crate::check_message!(@deprecated $field 6 "custom_error: the field 6 of this variant is never used in the error message"); };
+    (@unused $field:ident 7) => { $crate::check_message!(@deprecated $field 7 "custom_error: the field 7 of this variant is never used in the error message"); };
+    (@unused $field:ident 8) => { $crate::check_message!(@deprecated $field 8 "custom_error: the field 8 of this variant is never used in the error message"); };
+    (@unused $field:ident 9) => { $crate::check_message!(@deprecated $field 9 "custom_error: the field 9 of this variant is never used in the error message"); };
+    (@unused $field:ident 10) => { $crate::check_message!(@deprecated $field 10 "custom_error: the field 10 of this variant is never used in the error message"); };
+    (@unused $field:ident 11) => { $crate::check_message!(@deprecated $field 11 "custom_error: the field 11 of this variant is never used in the error message"); };
+    (@unused $field:ident 12) => { $crate::check_message!(@deprecated $field 12 "custom_error: the field 12 of this variant is never used in the error message"); };
+    (@unused $field:ident 13) => { $crate::check_message!(@deprecated $field 13 "custom_error: the field 13 of this variant is never used in the error message"); };
+    (@unused $field:ident 14) => { $crate::check_message!(@deprecated $field 14 "custom_error: the field 14 of this variant is never used in the error message"); };
+    (@unused $field:ident 15) => { $crate::check_message!(@deprecated $field 15 "custom_error: the field 15 of this variant is never used in the error message"); };
+    (@deprecated $field:ident $index:tt $note:literal) => {
+        #[allow(dead_code, non_snake_case)]
+        impl Fields<false, $index> {
+            #[deprecated(note = $note)]
+            const fn $field(self) {}
+        }
+    };
+    // The generic parameters of the error can be referenced in its messages
+    (@params ( $_errtype:ident $_params:tt [ $($arg:tt),* ] $_predicates:tt )) => {
+        [ $( stringify!($arg) ),* ]
+    };
+    (@used source $_conversion:tt $_name:tt $_msg:expr) => { true };
+    (@used maybe_source $_conversion:tt $_name:tt $_msg:expr) => { true };
+    (@used $_kind:ident location $_name:tt $_msg:expr) => { true };
+    (@used $_kind:ident backtrace $_name:tt $_msg:expr) => { true };
+    (@used $_kind:ident $_conversion:tt source $_msg:expr) => { true };
+    (@used $_kind:ident $_conversion:tt $name:tt $msg:expr) => {
+        $crate::private::uses_field($msg, stringify!($name))
+    };
+}
+
+#[cfg(feature = "std")]
+extern crate core;
+#[cfg(feature = "std")]
+use std::error::Error;
+#[cfg(not(feature = "std"))]
+use core::error::Error;
+#[cfg(feature = "derive")]
+extern crate custom_error_derive;
+
+/// Implements `Display`, `Error` and `From` for an ordinary enum or struct,
+/// like [`custom_error!`](macro.custom_error.html) does for the types it defines.
+/// This derive macro requires the `derive` feature of this crate.
+///
+/// The message of each enum variant, or of the struct, is given with `#[error("...")]`,
+/// using the same placeholders as `custom_error!`: named fields are referenced by their name,
+/// and tuple fields by their position.
+/// Unknown placeholders are reported at compile time.
+/// `#[error(transparent)]` forwards the message and the source to the single field.
+///
+/// The source of the error is the field marked with `#[source]`, or the field named `source`.
+/// `#[from]` also implements the conversion from the type of the field,
+/// the other fields being initialized with their default value (or with `#[default = value]`),
+/// or captured when they are a `&'static Location<'static>` or a `Backtrace`.
+/// The conversion from a field named `source` is implemented when its other fields
+/// have a `#[default = value]` or are captured.
+/// The single field of a tuple variant is its source if it implements `Error`.
+/// If this crate is renamed in `Cargo.toml`, its path is given with `#[custom_error(crate = path)]`.
+/// `#[exit = code]` gives the exit code of a variant, returned by `exit_code()`;
+/// the variants without one have the exit code 1.
+///
+/// The type must implement `Debug`, which is not derived.
+///
+/// ```
+/// use custom_error::CustomError;
+/// use std::{error::Error, io, num::ParseIntError};
+///
+/// #[derive(Debug, CustomError)]
+/// pub enum ConfigError {
+///     #[error("unable to read {path}")]
+///     Read { #[source] cause: io::Error, path: String },
+///     #[error("invalid number")]
+///     Parse { #[from] inner: ParseIntError },
+///     #[error("register {0:#04x} is out of range")]
+///     Register(u8),
+///     #[error(transparent)]
+///     Io(io::Error),
+/// }
+///
+/// let err: ConfigError = "x".parse::<u8>().unwrap_err().into();
+/// assert_eq!("invalid number", err.to_string());
+/// assert!(err.source().is_some());
+/// assert_eq!("register 0x0a is out of range", ConfigError::Register(10).to_string());
+///
+/// #[derive(Debug, CustomError)]
+/// #[error("line {line}: unexpected token")]
+/// pub struct SyntaxError {
+///     line: usize,
+/// }
+///
+/// assert_eq!("line 3: unexpected token", SyntaxError { line: 3 }.to_string());
+/// ```
+///
+/// ```compile_fail
+/// use custom_error::CustomError;
+///
+/// #[derive(Debug, CustomError)]
+/// enum MyError {
+///     // error: unknown placeholder `{nmae}` in the message of `Missing`; available fields: name
+///     #[error("{nmae} is missing")]
+///     Missing { name: String },
+/// }
+/// ```
+#[cfg(feature = "derive")]
+pub use custom_error_derive::CustomError;
+
+/// Description of a case of an error type generated by [`custom_error!`].
+///
+/// The cases of an error type are listed in its `VARIANTS` constant.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct VariantInfo {
+    /// The name of the case
+    pub name: &'static str,
+    /// The names of the fields of the case, or their positions for tuple cases
+    pub field_names: &'static [&'static str],
+    /// The types of the fields of the case, as they are written in the macro
+    pub field_types: &'static [&'static str],
+    /// The message of the case, or `None` if it is generated by custom code or transparent
+    pub message: Option<&'static str>,
+}
+
+/// A context selector, that creates an error of type `E` from a source and the fields it holds.
+///
+/// Context selectors are generated by [`custom_error!`] with the `#[context]` option,
+/// and used with [`ResultExt::context`] and [`OptionExt::context`].
+pub trait IntoError<E> {
+    /// The source of the error, or [`NoSource`] for error cases without a source
+    type Source;
+
+    /// Creates the error
+    fn into_error(self, source: Self::Source) -> E;
+}
+
+/// The source of the context selectors of error cases without a source
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct NoSource;
+
+/// Adds context to the errors of a `Result`
+pub trait ResultExt<T, S> {
+    /// Converts the error of the result with a context selector, that holds the other fields of the error
+    fn context<C: IntoError<E, Source = S>, E>(self, context: C) -> Result<T, E>;
+}
+
+impl<T, S> ResultExt<T, S> for Result<T, S> {
+    #[track_caller]
+    fn context<C: IntoError<E, Source = S>, E>(self, context: C) -> Result<T, E> {
+        match self {
+            Ok(value) => Ok(value),
+            Err(source) => Err(context.into_error(source)),
+        }
+    }
+}
+
+/// Converts an `Option` to a `Result`
+pub trait OptionExt<T> {
+    /// Converts `None` to an error created by a context selector of an error case without a source
+    fn context<C: IntoError<E, Source = NoSource>, E>(self, context: C) -> Result<T, E>;
+}
+
+impl<T> OptionExt<T> for Option<T> {
+    #[track_caller]
+    fn context<C: IntoError<E, Source = NoSource>, E>(self, context: C) -> Result<T, E> {
+        match self {
+            Some(value) => Ok(value),
+            None => Err(context.into_error(NoSource)),
+        }
+    }
+}
+
+/// Wraps an error to display it with the chain of its sources.
+///
+/// Its `Display` output has one line for the error and one for each of its sources:
+///
+/// ```text
+/// error: unable to read the configuration
+/// caused by: invalid digit found in string
+/// ```
+///
+/// The alternate form (`{:#}`) displays the chain on a single line:
+/// `unable to read the configuration: invalid digit found in string`.
+/// `Debug` displays the chain like `Display`, without the `error: ` prefix,
+/// so that `main` can return a `Result<(), Report<MyError>>`:
+/// errors are converted to reports by the `?` operator.
+pub struct Report<E> {
+    error: E,
+}
+
+impl<E: Error + 'static> Report<E> {
+    /// Wraps an error
+    pub fn new(error: E) -> Self {
+        Report { error }
+    }
+
+    /// Returns the wrapped error
+    pub fn error(&self) -> &E {
+        &self.error
+    }
+
+    /// Returns the wrapped error
+    pub fn into_error(self) -> E {
+        self.error
+    }
+
+    /// Iterates over the error and its sources
+    pub fn chain(&self) -> Chain<'_> {
+        Chain::new(&self.error)
+    }
+
+    fn fmt_chain(&self, formatter: &mut core::fmt::Formatter, prefix: &str) -> core::fmt::Result {
+        let mut chain = self.chain();
+        if let Some(error) = chain.next() {
+            write!(formatter, "{}{}", prefix, error)?;
+        }
+        for source in chain {
+            if formatter.alternate() {
+                write!(formatter, ": {}", source)?;
+            } else {
+                write!(formatter, "\ncaused by: {}", source)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<E: Error + 'static> From<E> for Report<E> {
+    fn from(error: E) -> Self {
+        Report::new(error)
+    }
+}
+
+impl<E: Error + 'static> core::fmt::Display for Report<E> {
+    fn fmt(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
+        let prefix = if formatter.alternate() { "" } else { "error: " };
+        self.fmt_chain(formatter, prefix)
+    }
+}
+
+impl<E: Error + 'static> core::fmt::Debug for Report<E> {
+    fn fmt(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
+        self.fmt_chain(formatter, "")
+    }
+}
+
+/// An iterator over an error and its sources, returned by [`Report::chain`]
+#[derive(Clone)]
+pub struct Chain<'a> {
+    next: Option<&'a (dyn Error + 'static)>,
+}
+
+impl<'a> Chain<'a> {
+    /// Iterates over an error and its sources
+    pub fn new(error: &'a (dyn Error + 'static)) -> Self {
+        Chain { next: Some(error) }
+    }
+}
+
+impl<'a> Iterator for Chain<'a> {
+    type Item = &'a (dyn Error + 'static);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let error = self.next?;
+        self.next = error.source();
+        Some(error)
+    }
+}
+
+/// Errors that determine the exit code of the process when it fails with them.
+///
+/// It is implemented by the error types defined by [`custom_error!`] or `#[derive(CustomError)]`,
+/// whose cases without an exit code exit with 1.
+pub trait Exit {
+    /// Returns the exit code of the process
+    fn exit_code(&self) -> i32;
+}
+
+/// The result of a `main` function, that exits with the exit code of its error.
+///
+/// When `main` fails, the error and the chain of its sources are printed to the standard error,
+/// like the `Display` output of [`Report`], and the process exits with the code of the error,
+/// or with 1 if the code is not between 0 and 255.
+///
+/// ```no_run
+/// #[macro_use] extern crate custom_error;
+/// use custom_error::Main;
+///
+/// custom_error!{CliError
+///     Usage[exit = 64]{arg: String} = "unknown argument {arg}",
+///     Io[exit = 74]{source: std::io::Error} = "input/output error",
+/// }
+///
+/// fn run() -> Result<(), CliError> {
+///     match std::env::args().nth(1) {
+///         Some(arg) => Err(CliError::Usage { arg }),
+///         None => Ok(()),
+///     }
+/// }
+///
+/// fn main() -> Main<CliError> {
+///     run().into()
+/// }
+/// ```
+#[cfg(feature = "std")]
+pub struct Main<E>(pub Result<(), E>);
+
+#[cfg(feature = "std")]
+impl<E> From<Result<(), E>> for Main<E> {
+    fn from(result: Result<(), E>) -> Self {
+        Main(result)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<E: Error + Exit + 'static> std::process::Termination for Main<E> {
+    fn report(self) -> std::process::ExitCode {
+        match self.0 {
+            Ok(()) => std::process::ExitCode::SUCCESS,
+            Err(error) => {
+                let code = error.exit_code();
+                eprintln!("{}", Report::new(error));
+                if (0..=255).contains(&code) {
+                    std::process::ExitCode::from(code as u8)
+                } else {
+                    std::process::ExitCode::FAILURE
+                }
+            }
+        }
+    }
+}
+
+/* Items used by the code generated by the macros of this crate.
+They are not part of the public API. */
+#[doc(hidden)]
+pub mod private {
+    #[cfg(feature = "derive")]
+    pub use custom_error_derive::methods;
+    #[cfg(feature = "std")]
+    pub use std::error::Error;
+    #[cfg(not(feature = "std"))]
+    pub use core::error::Error;
+    pub use core::fmt;
+    pub use core::option::Option;
+    pub use core::panic::Location;
+    pub use core::result::Result::{self, Err};
+    #[cfg(feature = "std")]
+    pub use std::backtrace::Backtrace;
+    #[cfg(feature = "nightly")]
+    pub use core::error::Request;
+    use core::marker::PhantomData;
+    use core::ops::Deref;
+
+    /// Converts a reference to a source field to an error trait object.
+    /// Source fields that are already trait objects (such as `Box<dyn Error + Send + Sync>`)
+    /// are converted too, thanks to auto-deref.
+    pub trait AsDynError {
+        fn as_dyn_error(&self) -> &(dyn Error + 'static);
+    }
+
+    impl<T: Error + 'static> AsDynError for T {
+        fn as_dyn_error(&self) -> &(dyn Error + 'static) { self }
+    }
+
+    impl AsDynError for dyn Error + 'static {
+        fn as_dyn_error(&self) -> &(dyn Error + 'static) { self }
+    }
+
+    impl AsDynError for dyn Error + Send + 'static {
+        fn as_dyn_error(&self) -> &(dyn Error + 'static) { self }
+    }
+
+    impl AsDynError for dyn Error + Sync + 'static {
+        fn as_dyn_error(&self) -> &(dyn Error + 'static) { self }
+    }
+
+    impl AsDynError for dyn Error + Send + Sync + 'static {
+        fn as_dyn_error(&self) -> &(dyn Error + 'static) { self }
+    }
+
+    /// Wraps the attribute of a tuple variant that may be the source of the error.
+    /// The `as_source` method is resolved using auto-ref:
+    /// `(&&SourceField(x)).as_source()` returns the attribute if it implements Error,
+    /// the error it points to if it is a smart pointer to an error trait object,
+    /// and None otherwise.
+    pub struct SourceField<'a, T: ?Sized + 'a>(pub &'a T);
+
+    pub trait IsSource<'a> {
+        fn as_source(&self) -> Option<&'a (dyn Error + 'static)>;
+    }
+
+    impl<'a, T: Error + 'static> IsSource<'a> for &SourceField<'a, T> {
+        fn as_source(&self) -> Option<&'a (dyn Error + 'static)> { Some(self.0) }
+    }
+
+    pub trait DerefSource<'a> {
+        fn as_source(&self) -> Option<&'a (dyn Error + 'static)>;
+    }
+
+    impl<'a, T> DerefSource<'a> for &&SourceField<'a, T>
+        where T: Deref, T::Target: AsDynError {
+        fn as_source(&self) -> Option<&'a (dyn Error + 'static)> { Some(self.0.deref().as_dyn_error()) }
+    }
+
+    pub trait NotSource<'a> {
+        fn as_source(&self) -> Option<&'a (dyn Error + 'static)>;
+    }
+
+    impl<'a, T: ?Sized> NotSource<'a> for SourceField<'a, T> {
+        fn as_source(&self) -> Option<&'a (dyn Error + 'static)> { None }
+    }
+
+    /// Wraps the attribute of a tuple variant in an error message.
+    /// It is formatted like the attribute itself,
+    /// except with the `Pointer` trait (`{:p}`), that does not write anything.
+    pub struct TupleField<'a, T: ?Sized + 'a>(pub &'a T);
+
+    macro_rules! forward_fmt {
+        ($($fmt_trait:ident)*) => {$(
+            impl<'a, T: ?Sized + fmt::$fmt_trait> fmt::$fmt_trait for TupleField<'a, T> {
+                fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                    fmt::$fmt_trait::fmt(self.0, formatter)
+                }
+            }
+        )*};
+    }
+
+    forward_fmt!(Display Debug Octal LowerHex UpperHex Binary LowerExp UpperExp);
+
+    impl<'a, T: ?Sized> fmt::Pointer for TupleField<'a, T> {
+        fn fmt(&self, _: &mut fmt::Formatter) -> fmt::Result { Ok(()) }
+    }
+
+    /// The formatting traits that the message of a generic error uses for one of its fields,
+    /// computed at compile time by `uses_field_as`.
+    pub struct Uses<
+        const DISPLAY: bool, const DEBUG: bool, const OCTAL: bool, const LOWER_HEX: bool,
+        const UPPER_HEX: bool, const BINARY: bool, const LOWER_EXP: bool, const UPPER_EXP: bool,
+    >;
+
+    /// Whether a formatting trait is used for a field
+    pub struct Used<const USED: bool>;
+
+    /// Formats a value of type `T` with the formatting trait `F` (such as `dyn fmt::Display`),
+    /// if it is used. The types of the fields only have to implement the traits that are used.
+    pub trait FormatIf<T: ?Sized, F: ?Sized> {
+        fn fmt(value: &T, formatter: &mut fmt::Formatter) -> fmt::Result;
+    }
+
+    impl<T: ?Sized, F: ?Sized> FormatIf<T, F> for Used<false> {
+        fn fmt(_: &T, _: &mut fmt::Formatter) -> fmt::Result { Ok(()) }
+    }
+
+    /// The formatting traits required for a field of type `T` in the message of a generic error.
+    /// The Display implementation of generic errors has a `Uses<...>: FieldFormats<T>` bound
+    /// for each field, so that the type parameters are only bound by the traits that are used.
+    pub trait FieldFormats<T: ?Sized> {
+        type Display: FormatIf<T, dyn fmt::Display>;
+        type Debug: FormatIf<T, dyn fmt::Debug>;
+        type Octal: FormatIf<T, dyn fmt::Octal>;
+        type LowerHex: FormatIf<T, dyn fmt::LowerHex>;
+        type UpperHex: FormatIf<T, dyn fmt::UpperHex>;
+        type Binary: FormatIf<T, dyn fmt::Binary>;
+        type LowerExp: FormatIf<T, dyn fmt::LowerExp>;
+        type UpperExp: FormatIf<T, dyn fmt::UpperExp>;
+    }
+
+    impl<
+        T: ?Sized,
+        const DISPLAY: bool, const DEBUG: bool, const OCTAL: bool, const LOWER_HEX: bool,
+        const UPPER_HEX: bool, const BINARY: bool, const LOWER_EXP: bool, const UPPER_EXP: bool,
+    > FieldFormats<T> for Uses<DISPLAY, DEBUG, OCTAL, LOWER_HEX, UPPER_HEX, BINARY, LOWER_EXP, UPPER_EXP>
+    where
+        Used<DISPLAY>: FormatIf<T, dyn fmt::Display>,
+        Used<DEBUG>: FormatIf<T, dyn fmt::Debug>,
+        Used<OCTAL>: FormatIf<T, dyn fmt::Octal>,
+        Used<LOWER_HEX>: FormatIf<T, dyn fmt::LowerHex>,
+        Used<UPPER_HEX>: FormatIf<T, dyn fmt::UpperHex>,
+        Used<BINARY>: FormatIf<T, dyn fmt::Binary>,
+        Used<LOWER_EXP>: FormatIf<T, dyn fmt::LowerExp>,
+        Used<UPPER_EXP>: FormatIf<T, dyn fmt::UpperExp>,
+    {
+        type Display = Used<DISPLAY>;
+        type Debug = Used<DEBUG>;
+        type Octal = Used<OCTAL>;
+        type LowerHex = Used<LOWER_HEX>;
+        type UpperHex = Used<UPPER_HEX>;
+        type Binary = Used<BINARY>;
+        type LowerExp = Used<LOWER_EXP>;
+        type UpperExp = Used<UPPER_EXP>;
+    }
+
+    /// Wraps a field in the message of a generic error.
+    /// It implements the formatting traits selected by `F`,
+    /// which are the ones that the message uses for the field.
+    pub struct GenericField<'a, T: ?Sized + 'a, F>(&'a T, PhantomData<F>);
+
+    impl<'a, T: ?Sized, F> GenericField<'a, T, F> {
+        pub fn new(value: &'a T) -> Self { GenericField(value, PhantomData) }
+    }
+
+    macro_rules! format_if {
+        ($($fmt_trait:ident)*) => {$(
+            impl<T: ?Sized + fmt::$fmt_trait> FormatIf<T, dyn fmt::$fmt_trait> for Used<true> {
+                fn fmt(value: &T, formatter: &mut fmt::Formatter) -> fmt::Result {
+                    fmt::$fmt_trait::fmt(value, formatter)
+                }
+            }
+
+            impl<'a, T: ?Sized, F: FieldFormats<T>> fmt::$fmt_trait for GenericField<'a, T, F> {
+                fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                    <F::$fmt_trait as FormatIf<T, dyn fmt::$fmt_trait>>::fmt(self.0, formatter)
+                }
+            }
+        )*};
+    }
+
+    format_if!(Display Debug Octal LowerHex UpperHex Binary LowerExp UpperExp);
+
+    // Like the fields of errors without type parameters, `{field:p}` formats the address of the field
+    impl<'a, T: ?Sized, F> fmt::Pointer for GenericField<'a, T, F> {
+        fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+            fmt::Pointer::fmt(&self.0, formatter)
+        }
+    }
+
+    /// The formatting traits, in the order of the parameters of `Uses`
+    #[derive(Clone, Copy)]
+    pub enum Format {
+        Display,
+        Debug,
+        Octal,
+        LowerHex,
+        UpperHex,
+        Binary,
+        LowerExp,
+        UpperExp,
+    }
+
+    /// A format argument referenced by an error message:
+    /// either a name or an explicit position (stored as a range of bytes in the message),
+    /// or an implicit position (`{}`).
+    #[derive(Clone, Copy)]
+    enum Argument {
+        Explicit(usize, usize),
+        Implicit(usize),
+    }
+
+    /// Position of the parser in an error message
+    #[derive(Clone, Copy)]
+    struct Cursor {
+        position: usize,
+        in_spec: bool, // true when the cursor is after the ':' of a placeholder
+        implicit: usize, // number of implicit positional arguments seen so far
+    }
+
+    const fn is_ident_byte(byte: u8) -> bool {
+        byte == b'_' || byte.is_ascii_alphanumeric()
+    }
+
+    /// Returns the next format argument referenced in the message after the cursor.
+    /// Arguments are referenced by placeholders (`{name}`, `{0}`, `{}`),
+    /// and by the width and precision of format specifiers (`{:width$}`, `{:.1$}`, `{:.*}`).
+    const fn next_argument(message: &[u8], mut cursor: Cursor) -> Option<(Argument, Cursor)> {
+        while cursor.position < message.len() {
+            let byte = message[cursor.position];
+            cursor.position += 1;
+            if cursor.in_spec {
+                if byte == b'}' {
+                    cursor.in_spec = false;
+                } else if byte == b'*' && message[cursor.position - 2] == b'.' {
+                    cursor.implicit += 1;
+                    return Some((Argument::Implicit(cursor.implicit - 1), cursor));
+                } else if byte == b'$' {
+                    let end = cursor.position - 1;
+                    let mut start = end;
+                    while is_ident_byte(message[start - 1]) { start -= 1; }
+                    if start < end { return Some((Argument::Explicit(start, end), cursor)); }
+                }
+            } else if byte == b'{' {
+                if cursor.position < message.len() && message[cursor.position] == b'{' {
+                    cursor.position += 1; // escaped brace
+                    continue;
+                }
+                let start = cursor.position;
+                while cursor.position < message.len()
+                    && message[cursor.position] != b'}' && message[cursor.position] != b':' {
+                    cursor.position += 1;
+                }
+                let end = cursor.position;
+                cursor.in_spec = cursor.position < message.len() && message[cursor.position] == b':';
+                cursor.position += 1;
+                if start < end { return Some((Argument::Explicit(start, end), cursor)); }
+                cursor.implicit += 1;
+                return Some((Argument::Implicit(cursor.implicit - 1), cursor));
+            }
+        }
+        None
+    }
+
+    /// Returns true if the field named `field` is designated by the argument
+    const fn is_field(message: &[u8], argument: Argument, field: &str) -> bool {
+        let field = field.as_bytes();
+        match argument {
+            Argument::Explicit(start, end) => {
+                if end - start != field.len() { return false; }
+                let mut i = 0;
+                while i < field.len() {
+                    if message[start + i] != field[i] { return false; }
+                    i += 1;
+                }
+                true
+            }
+            Argument::Implicit(mut index) => {
+                // The fields of tuple variants are named by their position
+                let mut i = field.len();
+                while i > 0 {
+                    i -= 1;
+                    if !field[i].is_ascii_digit() || (field[i] - b'0') as usize != index % 10 {
+                        return false;
+                    }
+                    index /= 10;
+                }
+                !field.is_empty() && index == 0
+            }
+        }
+    }
+
+    /// Returns true if the error message references the given field.
+    /// Fields whose name starts with an underscore are considered used.
+    pub const fn uses_field(message: &str, field: &str) -> bool {
+        if !field.is_empty() && field.as_bytes()[0] == b'_' { return true; }
+        let message = message.as_bytes();
+        let mut cursor = Cursor { position: 0, in_spec: false, implicit: 0 };
+        while let Some((argument, next)) = next_argument(message, cursor) {
+            if is_field(message, argument, field) { return true; }
+            cursor = next;
+        }
+        false
+    }
+
+    /// Returns the formatting trait of the placeholder whose format spec starts at `position`
+    /// (after the `:`), given by the last character of the spec
+    const fn spec_format(message: &[u8], mut position: usize) -> Format {
+        while position < message.len() && message[position] != b'}' { position += 1; }
+        match message[position - 1] {
+            b'?' => Format::Debug,
+            b'o' => Format::Octal,
+            b'x' => Format::LowerHex,
+            b'X' => Format::UpperHex,
+            b'b' => Format::Binary,
+            b'e' => Format::LowerExp,
+            b'E' => Format::UpperExp,
+            _ => Format::Display,
+        }
+    }
+
+    /// Returns true if the message of a generic error formats the given field with `format`.
+    /// The fields of generic errors are wrapped in `GenericField` when the message is written,
+    /// so they cannot be used as the width or precision of another placeholder.
+    pub const fn uses_field_as(message: &str, field: &str, format: Format) -> bool {
+        let message = message.as_bytes();
+        let mut cursor = Cursor { position: 0, in_spec: false, implicit: 0 };
+        let mut used = false;
+        while let Some((argument, next)) = next_argument(message, cursor) {
+            if is_field(message, argument, field) {
+                let last = message[next.position - 1];
+                if last == b'$' || last == b'*' {
+                    if let Format::Display = format {
+                        let error = ConstString { bytes: [0; 512], len: 0 }
+                            .push(b"custom_error: the field `")
+                            .push(field.as_bytes())
+                            .push(b"` of a generic error cannot be used as a width or precision");
+                        panic!("{}", error.as_str());
+                    }
+                } else if last == b':' {
+                    used |= spec_format(message, next.position) as u8 == format as u8;
+                } else {
+                    used |= format as u8 == Format::Display as u8;
+                }
+            }
+            cursor = next;
+        }
+        used
+    }
+
+    /// A string built at compile time, to report errors in error messages
+    struct ConstString {
+        bytes: [u8; 512],
+        len: usize,
+    }
+
+    impl ConstString {
+        const fn push(mut self, s: &[u8]) -> Self {
+            let mut i = 0;
+            while i < s.len() && self.len < self.bytes.len() {
+                self.bytes[self.len] = s[i];
+                self.len += 1;
+                i += 1;
+            }
+            self
+        }
+
+        const fn push_argument(self, message: &[u8], argument: Argument) -> Self {
+            match argument {
+                Argument::Explicit(start, end) => self.push(message.split_at(end).0.split_at(start).1),
+                Argument::Implicit(_) => self,
+            }
+        }
+
+        const fn push_u32(self, n: u32) -> Self {
+            let mut digits = [0; 10];
+            let mut start = digits.len();
+            let mut n = n;
+            loop {
+                start -= 1;
+                digits[start] = b'0' + (n % 10) as u8;
+                n /= 10;
+                if n == 0 { break; }
             }
+            self.push(digits.split_at(start).1)
         }
-    };
-    (
-        $_errtype:ident $( < $($_errtype_type_param:tt),* > )*,
-        $_field:ident,
-        $(
-            $_:ident,
-            $_repeated:ident,
-            $($_type:ident)::* $( < $($_type_param:tt),* > )*
-        ),*
-    ) => {}; // If the list of fields is not a single field named 'source', do nothing
-}
 
-#[doc(hidden)]
-#[macro_export]
-macro_rules! display_message {
-    ($formatter:expr, $($attr:ident),* | $msg:expr) => {
-        write!(
-            $formatter,
-            concat!($msg $(, "{", stringify!($attr), ":.0}" )*)
-            $( , $attr = $attr.to_string() )*
-        )?;
-    };
-    ($formatter:expr, $($attr:ident),* | ) => {};
-}
+        const fn as_str(&self) -> &str {
+            match core::str::from_utf8(self.bytes.split_at(self.len).0) {
+                Ok(s) => s,
+                Err(_) => "custom_error: invalid placeholder in an error message",
+            }
+        }
+    }
 
-/* This macro, given a list of generic parameters and type
-bounds, adds the type bounds to all generic type parameters
-(and leaves the generic lifetime parameters unchanged) */
-#[doc(hidden)]
-#[macro_export]
-macro_rules! add_type_bounds {
-    (
-        ( $typ:ident $(, $rest:tt)* ) // type parameter
-        ( $($bounds:tt)* )
-        { $($prefix:tt)* }
-        { $($suffix:tt)* }
-    ) => {
-        add_type_bounds!{
-            ( $(, $rest)* )
-            ( $($bounds)* )
-            { $($prefix)* $typ : $($bounds)*}
-            { $($suffix)* }
+    /// Checks at compile time that all the placeholders in the message of the error case
+    /// `variant` reference one of its `fields`, or one of the generic `params` of the error
+    /// (such as const parameters).
+    /// The fields of tuple variants are named by their position.
+    pub const fn check_message(variant: &str, message: &str, fields: &[&str], params: &[&str]) {
+        let bytes = message.as_bytes();
+        let mut cursor = Cursor { position: 0, in_spec: false, implicit: 0 };
+        while let Some((argument, next)) = next_argument(bytes, cursor) {
+            let mut i = 0;
+            while i < fields.len() && !is_field(bytes, argument, fields[i]) { i += 1; }
+            let mut j = 0;
+            while j < params.len() && !is_field(bytes, argument, params[j]) { j += 1; }
+            if i == fields.len() && j == params.len() {
+                let mut error = ConstString { bytes: [0; 512], len: 0 }
+                    .push(b"custom_error: unknown placeholder `{")
+                    .push_argument(bytes, argument)
+                    .push(b"}` in the message of `")
+                    .push(variant.as_bytes());
+                if fields.is_empty() {
+                    error = error.push(b"`, which has no fields");
+                } else {
+                    error = error.push(b"`; available fields: ");
+                    let mut i = 0;
+                    while i < fields.len() {
+                        if i > 0 { error = error.push(b", "); }
+                        error = error.push(fields[i].as_bytes());
+                        i += 1;
+                    }
+                }
+                panic!("{}", error.as_str());
+            }
+            cursor = next;
         }
-    };
-    (
-        ( $lifetime:tt $(, $rest:tt)* ) // lifetime parameter
-        ( $($bounds:tt)* )
-        { $($prefix:tt)* }
-        { $($suffix:tt)* }
-    ) => {
-        add_type_bounds!{
-            ( $(, $rest)* )
-            ( $($bounds)* )
-            { $($prefix)* $lifetime }
-            { $($suffix)* }
+    }
+
+    /// Checks at compile time that all the cases of the error `errtype` have different codes
+    pub const fn check_codes(errtype: &str, codes: &[(&str, &str)]) {
+        let mut i = 0;
+        while i < codes.len() {
+            let mut j = i + 1;
+            while j < codes.len() {
+                if is_same(codes[i].1.as_bytes(), codes[j].1.as_bytes()) {
+                    let error = ConstString { bytes: [0; 512], len: 0 }
+                        .push(b"custom_error: the code `")
+                        .push(codes[i].1.as_bytes());
+                    duplicate_code(error, errtype, codes[i].0, codes[j].0);
+                }
+                j += 1;
+            }
+            i += 1;
         }
-    };
-    (
-        ( , $($rest:tt)* ) // add the comma to the prefix
-        ( $($bounds:tt)* )
-        { $($prefix:tt)* }
-        { $($suffix:tt)* }
-    ) => {
-        add_type_bounds!{
-            ( $($rest)* )
-            ( $($bounds)* )
-            { $($prefix)* , }
-            { $($suffix)* }
+    }
+
+    /// Checks at compile time that all the cases of the error `errtype` have different numeric codes
+    pub const fn check_code_nums(errtype: &str, codes: &[(&str, u32)]) {
+        let mut i = 0;
+        while i < codes.len() {
+            let mut j = i + 1;
+            while j < codes.len() {
+                if codes[i].1 == codes[j].1 {
+                    let error = ConstString { bytes: [0; 512], len: 0 }
+                        .push(b"custom_error: the numeric code `")
+                        .push_u32(codes[i].1);
+                    duplicate_code(error, errtype, codes[i].0, codes[j].0);
+                }
+                j += 1;
+            }
+            i += 1;
         }
-    };
-    (
-        (  ) // no more generic params to consume
-        ( $($bounds:tt)* )
-        { $($prefix:tt)* }
-        { $($suffix:tt)* }
-    ) => {
-        $($prefix)* $($suffix)*
+    }
+
+    const fn is_same(a: &[u8], b: &[u8]) -> bool {
+        if a.len() != b.len() { return false; }
+        let mut i = 0;
+        while i < a.len() {
+            if a[i] != b[i] { return false; }
+            i += 1;
+        }
+        true
+    }
+
+    const fn duplicate_code(error: ConstString, errtype: &str, first: &str, second: &str) {
+        let error = error
+            .push(b"` of `")
+            .push(errtype.as_bytes())
+            .push(b"` is used by both `")
+            .push(first.as_bytes())
+            .push(b"` and `")
+            .push(second.as_bytes())
+            .push(b"`");
+        panic!("{}", error.as_str());
     }
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "std"))]
 mod tests {
     #[test]
     fn single_error_case() {
@@ -353,6 +3316,196 @@ mod tests {
         assert_eq!("3 2 1", E::X { a: 1, b: 2, c: 3 }.to_string());
     }
 
+    #[test]
+    fn format_specifiers() {
+        use std::path::PathBuf;
+        custom_error!(E
+            Hex{code:u32}          = "code {code:#x} {code:08b}",
+            Precision{ratio:f64}   = "ratio {ratio:.2} {ratio:e}",
+            Debug{path:PathBuf}    = "cannot open {path:?}",
+            Align{name:String}     = "[{name:>6}] [{name:-<6}] [{name:^6.2}]",
+        );
+        assert_eq!("code 0xff 11111111", E::Hex { code: 255 }.to_string());
+        assert_eq!("ratio 0.33 3.3e-1", E::Precision { ratio: 0.33 }.to_string());
+        assert_eq!(r#"cannot open "/tmp""#, E::Debug { path: "/tmp".into() }.to_string());
+        assert_eq!("[   abc] [abc---] [  ab  ]", E::Align { name: "abc".into() }.to_string());
+    }
+
+    #[test]
+    fn source_and_from_markers() {
+        use std::{io, fmt, error::Error};
+        custom_error! {MyError
+            Read{#[source] cause: io::Error, path: String}     = "unable to read {path}",
+            Format{#[from] inner: fmt::Error}                  = "formatting error",
+            Parse(#[from] std::num::ParseIntError)             = "invalid number",
+            Code(#[source] io::Error, u8)                      = "error {1}",
+            Named{#[source] source: std::num::ParseFloatError} = "invalid float",
+        }
+        let read = MyError::Read { cause: io::ErrorKind::NotFound.into(), path: "a".into() };
+        assert_eq!("unable to read a", read.to_string());
+        assert!(read.source().is_some());
+
+        let format = MyError::from(fmt::Error);
+        assert_eq!("formatting error", format.to_string());
+        assert!(format.source().is_some());
+
+        let parse: MyError = "x".parse::<u8>().unwrap_err().into();
+        assert_eq!("invalid number", parse.to_string());
+        assert_eq!(
+            "invalid digit found in string",
+            parse.source().unwrap().to_string()
+        );
+
+        let code = MyError::Code(io::ErrorKind::Other.into(), 2);
+        assert!(code.source().is_some());
+
+        let named = MyError::Named { source: "x".parse::<f64>().unwrap_err() };
+        assert!(named.source().is_some());
+    }
+
+    #[test]
+    fn conversion_with_other_fields() {
+        use std::{io, error::Error, panic::Location, path::PathBuf};
+        #[derive(Debug)]
+        struct NoDefault;
+        custom_error! {MyError
+            Io{source: io::Error, #[default = None] path: Option<PathBuf>} = "unable to read {path:?}",
+            Parse{#[from] inner: std::num::ParseIntError, #[default = 10] radix: u32}
+                                                               = "invalid base {radix} number",
+            Format(#[from] std::fmt::Error, &'static Location<'static>) = "formatting error at {1}",
+            // The conversion is not possible, because NoDefault does not implement Default
+            Env{#[from] source: std::env::VarError, _value: NoDefault} = "invalid environment variable",
+            // The conversion is only implemented for a field named `source` if the other fields
+            // have a value or are captured
+            #[allow(dead_code)]
+            Utf8{source: std::str::Utf8Error, path: PathBuf}   = "invalid file {path:?}",
+            Other{source: std::str::Utf8Error}                 = "invalid text",
+        }
+        let io = MyError::from(io::Error::from(io::ErrorKind::NotFound));
+        assert_eq!("unable to read None", io.to_string());
+        assert!(io.source().is_some());
+
+        let parse: MyError = "x".parse::<u8>().unwrap_err().into();
+        assert_eq!("invalid base 10 number", parse.to_string());
+
+        #[allow(invalid_from_utf8)]
+        let utf8 = MyError::from(std::str::from_utf8(&[0xff]).unwrap_err());
+        assert_eq!("invalid text", utf8.to_string());
+
+        fn format() -> Result<(), MyError> { Err(std::fmt::Error)?; Ok(()) }
+        let line = line!() - 1;
+        match format().unwrap_err() {
+            MyError::Format(_, location) => assert_eq!(line, location.line()),
+            _ => panic!("expected a formatting error"),
+        }
+    }
+
+    #[test]
+    fn backtrace_fields() {
+        use std::{io, backtrace::{Backtrace, BacktraceStatus}};
+        custom_error! {MyError
+            Internal{source: io::Error, backtrace: Backtrace} = "internal error",
+            Tuple(#[from] std::fmt::Error, std::backtrace::Backtrace) = "formatting error",
+            Other = "other error",
+        }
+        let internal = MyError::from(io::Error::from(io::ErrorKind::Other));
+        let status = internal.backtrace().unwrap().status();
+        assert_eq!(Backtrace::capture().status(), status);
+        let tuple = MyError::from(std::fmt::Error);
+        assert!(tuple.backtrace().is_some());
+        assert!(MyError::Other.backtrace().is_none());
+        assert_ne!(BacktraceStatus::Unsupported, Backtrace::force_capture().status());
+    }
+
+    #[test]
+    #[cfg(feature = "nightly")]
+    fn provided_backtrace() {
+        use std::{io, backtrace::Backtrace, error::request_ref};
+        custom_error! {MyError
+            Internal{source: io::Error, backtrace: Backtrace} = "internal error",
+            Other = "other error",
+        }
+        let internal = MyError::from(io::Error::from(io::ErrorKind::Other));
+        assert!(request_ref::<Backtrace>(&internal).is_some());
+        assert!(request_ref::<Backtrace>(&MyError::Other).is_none());
+    }
+
+    #[test]
+    fn transparent_variants() {
+        use std::{io, error::Error};
+        custom_error! {Inner
+            Io{source: io::Error} = "inner io error",
+        }
+        custom_error! {MyError
+            Wrapped{source: Inner}                        = transparent,
+            Parse(#[from] std::num::ParseIntError)        = transparent,
+            Boxed{inner: Box<dyn Error + Send + Sync>}    = transparent,
+            Other                                         = "other error",
+        }
+        custom_error! {Wrapper{source: MyError} = transparent}
+
+        let inner = Inner::from(io::Error::other("disk full"));
+        let wrapped = MyError::from(inner);
+        assert_eq!("inner io error", wrapped.to_string());
+        assert_eq!("disk full", wrapped.source().unwrap().to_string());
+
+        let parse: MyError = "x".parse::<u8>().unwrap_err().into();
+        assert_eq!("invalid digit found in string", parse.to_string());
+        assert!(parse.source().is_none());
+
+        let boxed = MyError::Boxed { inner: "boxed error".into() };
+        assert_eq!("boxed error", boxed.to_string());
+        assert!(boxed.source().is_none());
+
+        let wrapper = Wrapper::from(MyError::Other);
+        assert_eq!("other error", wrapper.to_string());
+    }
+
+    #[test]
+    fn field_attributes() {
+        custom_error! {
+            #[derive(Default)]
+            MyError {
+                /// The line of the error
+                #[allow(dead_code)]
+                line: usize,
+                #[doc = "The column of the error"]
+                column: usize
+            } = "error at {line}:{column}"
+        }
+        assert_eq!("error at 0:0", MyError::default().to_string());
+    }
+
+    #[test]
+    fn message_placeholders() {
+        use private::uses_field;
+        assert!(uses_field("{a} {b:?}", "b"));
+        assert!(!uses_field("{ab} {{b}}", "b"));
+        assert!(uses_field("{a:>b$}", "b"));
+        assert!(uses_field("{:.1$} {:.*}", "1"));
+        assert!(uses_field("{} {:.*}", "2"));
+        assert!(!uses_field("{0} {0:*^.1}", "1"));
+        assert!(uses_field("", "_unused"));
+    }
+
+    #[test]
+    fn checked_messages() {
+        custom_error! {E
+            Named{a: u8, width: usize} = "{a:>width$} {{a}}",
+            Tuple(u8, u8)              = "{0}, {1:?} {}",
+            Unit                       = "{{unit}}",
+        }
+        assert_eq!("  1 {a}", E::Named { a: 1, width: 3 }.to_string());
+        assert_eq!("1, 2 1", E::Tuple(1, 2).to_string());
+        assert_eq!("{unit}", E::Unit.to_string());
+    }
+
+    #[test]
+    fn attributes_without_display() {
+        custom_error!(E X{_bytes: Vec<u8>, len: usize} = "{len} bytes");
+        assert_eq!("2 bytes", E::X { _bytes: vec![1, 2], len: 2 }.to_string());
+    }
+
     #[test]
     fn source() {
         use std::{io, error::Error};
@@ -373,11 +3526,11 @@ mod tests {
     }
 
     #[test]
-    #[allow(dead_code)]
+    #[allow(dead_code, deprecated)] // unused fields
     fn with_source_and_others() {
         use std::{io, error::Error};
         custom_error!(MyError Zero="", One{x:u8}="", Two{x:u8, source:io::Error}="{x}");
-        fn source() -> io::Error { io::ErrorKind::AlreadyExists.into() };
+        fn source() -> io::Error { io::ErrorKind::AlreadyExists.into() }
         let my_err = MyError::Two { x: 42, source: source() };
         assert_eq!("42", my_err.to_string());
         assert_eq!(source().to_string(), my_err.source().unwrap().to_string());
@@ -389,6 +3542,274 @@ mod tests {
         assert_eq!("case1", my_mod::MyError::Case1.to_string())
     }
 
+    #[test]
+    fn visibility() {
+        mod outer {
+            pub mod inner {
+                custom_error! {pub(crate) CrateError Case="case"}
+                custom_error! {pub(super) SuperError Case="case"}
+                custom_error! {pub(in super::super) PathError{pub(crate) code: u8, private: u8} = "{code} {private}"}
+                custom_error! {pub FieldsError{
+                    /// The line of the error
+                    pub line: usize,
+                    pub(super) column: usize,
+                    pub(in super) file: &'static str,
+                    _private: bool
+                } = "{file}:{line}:{column}"}
+                custom_error! {pub(crate) TupleError(pub u8, pub(crate) &'static str) = "{0} {1}"}
+
+                pub fn fields() -> FieldsError {
+                    FieldsError { line: 1, column: 2, file: "a.rs", _private: true }
+                }
+                pub fn path_error() -> PathError { PathError { code: 1, private: 2 } }
+            }
+            pub fn column() -> usize { inner::fields().column }
+            pub fn file() -> &'static str { inner::fields().file }
+            pub fn super_error() -> String { inner::SuperError::Case.to_string() }
+        }
+        assert_eq!("case", outer::inner::CrateError::Case.to_string());
+        assert_eq!("case", outer::super_error());
+        assert_eq!(1, outer::inner::path_error().code);
+        assert_eq!("1 2", outer::inner::path_error().to_string());
+        assert_eq!("a.rs:1:2", outer::inner::fields().to_string());
+        assert_eq!(1, outer::inner::fields().line);
+        assert_eq!(2, outer::column());
+        assert_eq!("a.rs", outer::file());
+        let tuple = outer::inner::TupleError(1, "x");
+        assert_eq!((1, "x"), (tuple.0, tuple.1));
+        assert_eq!("1 x", tuple.to_string());
+    }
+
+    #[test]
+    fn error_kind() {
+        use std::collections::HashSet;
+        use std::io;
+
+        custom_error! {
+            /// An error with kinds
+            #[kind = MyErrorKind]
+            #[derive(Clone)]
+            MyError
+                Io{source: std::rc::Rc<io::Error>} = "io",
+                Parse(#[from] std::num::ParseIntError) = "parse",
+                #[cfg(any())]
+                Disabled{x: NotAType}              = "disabled",
+                Unit                               = "unit",
+        }
+        let io = MyError::Io { source: io::Error::other("io").into() };
+        assert_eq!(MyErrorKind::Io, io.clone().kind());
+        assert_eq!(MyErrorKind::Parse, MyError::from("x".parse::<u8>().unwrap_err()).kind());
+        assert_eq!(MyErrorKind::Unit, MyError::Unit.kind());
+        assert_eq!("Io", io.kind().to_string());
+        let kinds: HashSet<_> = vec![MyError::Unit.kind(), io.kind(), MyError::Unit.kind()].into_iter().collect();
+        assert_eq!(2, kinds.len());
+
+        custom_error! {#[kind = GenericKind] pub(crate) Generic<T> Value{value: T} = "{value}", Other = "other"}
+        assert_eq!(GenericKind::Value, Generic::Value { value: 1 }.kind());
+        assert_eq!(GenericKind::Other, Generic::<()>::Other.kind());
+
+        custom_error! {#[kind = StructKind] Struct(u8) = "{0}"}
+        assert_eq!("Struct", Struct(1).kind().to_string());
+    }
+
+    #[test]
+    fn error_codes() {
+        custom_error! {
+            #[kind = ApiErrorKind]
+            ApiError
+                NotFound[code = "E0404", num = 404]{id: u64}        = "no item with id {id}",
+                Invalid[num = 400, code = "E0400"](String)           = "invalid request: {0}",
+                #[cfg(any())]
+                Disabled[code = "E0404", num = 404]                  = "disabled",
+                Io[code = "E0500", num = 500]{source: std::io::Error} = transparent,
+        }
+        let not_found = ApiError::NotFound { id: 3 };
+        assert_eq!(("E0404", 404), (not_found.code(), not_found.code_num()));
+        let invalid = ApiError::Invalid("x".into());
+        assert_eq!(("E0400", 400), (invalid.code(), invalid.code_num()));
+        assert_eq!(500, ApiError::from(std::io::Error::other("io")).code_num());
+        assert_eq!(Some(ApiErrorKind::NotFound), ApiErrorKind::from_code("E0404"));
+        assert_eq!(Some(ApiErrorKind::Io), ApiErrorKind::from_code(ApiError::from(std::io::Error::other("io")).code()));
+        assert_eq!(None, ApiErrorKind::from_code("E0000"));
+
+        custom_error! {Generic<T>[num = 1]{value: T} = "{value}"}
+        assert_eq!(1, Generic { value: 'x' }.code_num());
+    }
+
+    #[test]
+    fn result_alias() {
+        // Each module can define one error with the #[result] option
+        mod parser {
+            custom_error! {
+                #[result]
+                pub ParseError
+                    Empty               = "empty input",
+                    TooLong{len: usize} = "input of {len} bytes",
+                    Invalid(char)       = "invalid character {0:?}",
+            }
+
+            pub fn parse(input: &str) -> Result<usize> {
+                ensure!(!input.is_empty(), Empty);
+                ensure!(input.len() < 10, TooLong { len: input.len() });
+                if let Some(c) = input.chars().find(|c| !c.is_ascii_digit()) {
+                    bail!(Invalid(c));
+                }
+                Ok(input.parse().unwrap())
+            }
+
+            custom_error! {pub Outer Parser{source: ParseError} = "parse error"}
+
+            pub fn parse_outer(input: &str) -> ::std::result::Result<usize, Outer> {
+                ensure!(input.len() < 10, TooLong { len: input.len() });
+                Ok(parse(input)?)
+            }
+
+            pub mod digits {
+                // The macros refer to the error type by its name
+                use super::ParseError;
+
+                pub fn digit(c: char) -> super::Result<u32> {
+                    ensure!(c != ' ', Empty);
+                    match c.to_digit(10) {
+                        Some(digit) => Ok(digit),
+                        None => bail!(Invalid(c)),
+                    }
+                }
+            }
+        }
+
+        mod lines {
+            custom_error! {#[result] pub LineError{line: u32} = "error at line {line}"}
+
+            pub fn line(line: u32) -> Result<u32> {
+                ensure!(line > 0, LineError { line });
+                bail!(LineError { line })
+            }
+        }
+
+        assert_eq!(12, parser::parse("12").unwrap());
+        assert_eq!("empty input", parser::parse("").unwrap_err().to_string());
+        assert_eq!("input of 12 bytes", parser::parse("123456789012").unwrap_err().to_string());
+        assert_eq!("invalid character 'x'", parser::parse("1x").unwrap_err().to_string());
+        assert_eq!("parse error", parser::parse_outer("123456789012").unwrap_err().to_string());
+        assert_eq!(7, parser::digits::digit('7').unwrap());
+        assert_eq!("empty input", parser::digits::digit(' ').unwrap_err().to_string());
+        assert_eq!("invalid character 'x'", parser::digits::digit('x').unwrap_err().to_string());
+        let _: parser::Result<()> = Ok(());
+        let _: lines::Result<()> = lines::line(1).map(drop);
+        assert_eq!("error at line 0", lines::line(0).unwrap_err().to_string());
+        assert_eq!("error at line 3", lines::line(3).unwrap_err().to_string());
+    }
+
+    #[test]
+    fn exit_codes() {
+        use super::{Exit, Main};
+        use std::process::{ExitCode, Termination};
+
+        custom_error! {CliError
+            Usage[exit = 64]{arg: String}          = "unknown argument {arg}",
+            Io[exit = 74]{source: std::io::Error}  = "input/output error",
+            #[cfg(any())]
+            Disabled[exit = 2]                     = "disabled",
+            Other                                  = "other error",
+            Negative[exit = -1]                    = "negative code",
+        }
+        assert_eq!(64, CliError::Usage { arg: "-x".into() }.exit_code());
+        assert_eq!(74, Exit::exit_code(&CliError::from(std::io::Error::other("disk full"))));
+        assert_eq!(1, CliError::Other.exit_code());
+
+        assert!(ExitCode::SUCCESS == Main::<CliError>::from(Ok(())).report());
+        assert!(ExitCode::from(64) == Main(Err(CliError::Usage { arg: "-x".into() })).report());
+        assert!(ExitCode::FAILURE == Main(Err(CliError::Negative)).report());
+
+        custom_error! {Generic<T>[exit = 3]{value: T} = "{value}"}
+        assert_eq!(3, Generic { value: 1 }.exit_code());
+
+        // Errors without exit codes exit with 1
+        custom_error! {Plain Failed = "failed", Refused{user: String} = "{user} was refused"}
+        custom_error! {Single{path: String} = "unable to open {path}"}
+        assert_eq!(1, Plain::Failed.exit_code());
+        assert!(ExitCode::FAILURE == Main(Err(Plain::Refused { user: "x".into() })).report());
+        assert!(ExitCode::SUCCESS == Main::<Plain>::from(Ok(())).report());
+        assert!(ExitCode::FAILURE == Main(Err(Single { path: "x".into() })).report());
+    }
+
+    #[test]
+    fn report() {
+        use super::Report;
+        use std::io;
+
+        custom_error! {ConfigError
+            Read{source: io::Error} = "unable to read the configuration",
+            Invalid                 = "invalid configuration",
+        }
+        custom_error! {AppError Config{source: ConfigError} = "unable to start"}
+
+        let error = AppError::from(ConfigError::from(io::Error::other("disk full")));
+        let report = Report::new(error);
+        let messages: Vec<_> = report.chain().map(|error| error.to_string()).collect();
+        assert_eq!(vec!["unable to start", "unable to read the configuration", "disk full"], messages);
+        assert_eq!(
+            "error: unable to start\ncaused by: unable to read the configuration\ncaused by: disk full",
+            report.to_string()
+        );
+        assert_eq!("unable to start: unable to read the configuration: disk full", format!("{:#}", report));
+        assert_eq!(
+            "unable to start\ncaused by: unable to read the configuration\ncaused by: disk full",
+            format!("{:?}", report)
+        );
+
+        fn run() -> Result<(), Report<ConfigError>> {
+            Err(ConfigError::Invalid)?;
+            Ok(())
+        }
+        let report = run().unwrap_err();
+        assert_eq!("error: invalid configuration", report.to_string());
+        assert_eq!(1, report.chain().count());
+        assert_eq!("invalid configuration", report.into_error().to_string());
+    }
+
+    #[test]
+    fn variants() {
+        use super::VariantInfo;
+        use std::io;
+
+        custom_error! {MyError
+            #[allow(dead_code)]
+            Io{source: io::Error, path: Vec<u8>} = "unable to read {path:?}",
+            Tuple(u8, &'static str)               = "{0} {1}",
+            #[cfg(any())]
+            Disabled                              = "disabled",
+            #[allow(dead_code)]
+            Custom{code: u8}                      = @{ code.to_string() },
+            #[allow(dead_code)]
+            Transparent(std::fmt::Error)          = transparent,
+            Unit                                  = "unit",
+        }
+        assert_eq!("Tuple", MyError::Tuple(1, "x").variant_name());
+        assert_eq!("Unit", MyError::Unit.variant_name());
+        let names: Vec<_> = MyError::VARIANTS.iter().map(|variant| variant.name).collect();
+        assert_eq!(vec!["Io", "Tuple", "Custom", "Transparent", "Unit"], names);
+        assert_eq!(
+            VariantInfo {
+                name: "Io",
+                field_names: &["source", "path"],
+                field_types: &["io::Error", "Vec<u8>"],
+                message: Some("unable to read {path:?}"),
+            },
+            MyError::VARIANTS[0]
+        );
+        assert_eq!(&["0", "1"], MyError::VARIANTS[1].field_names);
+        assert_eq!(&["u8", "&'static str"], MyError::VARIANTS[1].field_types);
+        assert_eq!(None, MyError::VARIANTS[2].message);
+        assert_eq!(None, MyError::VARIANTS[3].message);
+        assert!(MyError::VARIANTS[4].field_names.is_empty());
+
+        custom_error! {Generic<T>{value: T} = "{value}"}
+        assert_eq!("Generic", Generic { value: 1 }.variant_name());
+        assert_eq!(&["T"], Generic::<u8>::VARIANTS[0].field_types);
+    }
+
     #[test]
     fn generic_error() {
         custom_error! {MyError<X,Y> E1{x:X,y:Y}="x={x} y={y}", E2="e2"}
@@ -469,6 +3890,245 @@ mod tests {
     }
 
     #[test]
+    fn type_and_variant_attributes() {
+        custom_error! {
+            /// My error type
+            #[derive(Clone, PartialEq, Eq, Hash)]
+            #[non_exhaustive]
+            pub MyError
+                /// First case
+                A = "a",
+                /// Second case
+                #[allow(non_camel_case_types)]
+                b{x:u8} = "b{x}"
+        }
+        let err = MyError::b { x: 1 };
+        assert_eq!(err.clone(), err);
+        assert_ne!(MyError::A, err);
+        assert_eq!("b1", err.to_string());
+    }
+
+    #[test]
+    fn cfg_variant() {
+        custom_error! {MyError
+            #[cfg(any())]
+            Disabled{source: DoesNotExist} = "disabled",
+            #[cfg(all())]
+            Enabled = "enabled"
+        }
+        match MyError::Enabled {
+            MyError::Enabled => {}
+        }
+        assert_eq!("enabled", MyError::Enabled.to_string());
+    }
+
+    #[test]
+    fn deprecated_variant() {
+        use std::io;
+        custom_error! {
+            #[kind = MyErrorKind]
+            MyError
+                #[deprecated(note = "use New instead")]
+                Old[code = "E1", num = 1, exit = 3]{source: io::Error} = "old",
+                New[code = "E2", num = 2] = "new"
+        }
+        let err: MyError = io::Error::from(io::ErrorKind::Other).into();
+        assert_eq!("old", err.to_string());
+        assert_eq!(MyErrorKind::New, MyError::New.kind());
+        assert_eq!(("E2", 2), (MyError::New.code(), MyError::New.code_num()));
+        assert_eq!(3, err.exit_code());
+        assert_eq!("new", MyError::New.to_string());
+    }
+
+    #[test]
+    fn arbitrary_field_types() {
+        custom_error! {MyError<'a>
+            Borrowed{input: &'a str}               = "invalid input: {input}",
+            Bytes{bytes: &'a [u8]}                 = @{ format!("{} bytes", bytes.len()) },
+            Array{magic: [u8; 4]}                  = @{ format!("bad magic: {:?}", magic) },
+            Tuple{pos: (u32, u32)}                 = @{ format!("at {}:{}", pos.0, pos.1) },
+            Function{callback: fn() -> u8}         = @{ callback().to_string() },
+            Absolute{code: ::std::primitive::u16}  = "code {code}",
+            Qualified{n: <u8 as ::std::ops::Add>::Output} = "n={n}",
+        }
+        fn seven() -> u8 { 7 }
+        assert_eq!("invalid input: x", MyError::Borrowed { input: "x" }.to_string());
+        assert_eq!("2 bytes", MyError::Bytes { bytes: b"xy" }.to_string());
+        assert_eq!("bad magic: [1, 2, 3, 4]", MyError::Array { magic: [1, 2, 3, 4] }.to_string());
+        assert_eq!("at 1:2", MyError::Tuple { pos: (1, 2) }.to_string());
+        assert_eq!("7", MyError::Function { callback: seven }.to_string());
+        assert_eq!("code 3", MyError::Absolute { code: 3 }.to_string());
+        assert_eq!("n=4", MyError::Qualified { n: 4 }.to_string());
+    }
+
+    #[test]
+    fn boxed_source() {
+        use std::error::Error;
+        type BoxError = Box<dyn Error + Send + Sync>;
+        custom_error! {MyError
+            Boxed{source: BoxError} = "boxed",
+            Io{source: ::std::io::Error} = "io"
+        }
+        let err: MyError = BoxError::from("inner error").into();
+        assert_eq!("inner error", err.source().unwrap().to_string());
+        let err: MyError = ::std::io::Error::from(::std::io::ErrorKind::Other).into();
+        assert_eq!("io", err.to_string());
+        assert!(err.source().is_some());
+    }
+
+    #[test]
+    fn struct_error() {
+        custom_error! {MyError{line: usize, column: usize} = "error at {line}:{column}"}
+        assert_eq!("error at 1:2", MyError { line: 1, column: 2 }.to_string());
+    }
+
+    #[test]
+    fn unit_struct_error() {
+        mod my_mod { custom_error! {pub MyError = "my error"} }
+        assert_eq!("my error", my_mod::MyError.to_string());
+    }
+
+    #[test]
+    fn struct_error_source() {
+        use std::{io, error::Error};
+        custom_error! {MyError{source: io::Error} = @{ format!("io: {:?}", source.kind()) }}
+        let err = MyError::from(io::Error::from(io::ErrorKind::NotFound));
+        assert_eq!("io: NotFound", err.to_string());
+        assert_eq!(
+            io::Error::from(io::ErrorKind::NotFound).to_string(),
+            err.source().unwrap().to_string()
+        );
+    }
+
+    #[test]
+    fn generic_struct_error() {
+        custom_error! {MyError<'a, T>{input: &'a str, value: T} = "{input}: {value}"}
+        assert_eq!("x: 42", MyError { input: "x", value: 42 }.to_string());
+    }
+
+    #[test]
+    fn tuple_variants() {
+        use std::{io, time::Duration, error::Error};
+        custom_error! {MyError
+            Timeout(Duration)      = "timed out after {0:?}",
+            Io(io::Error)          = "io failure",
+            Format(#[from] std::fmt::Error) = "formatting failure",
+            Position(u32, String)  = "{1} at line {0}",
+            Implicit(u8, u8)       = "{} then {}",
+            Code(u32)              = "code {0:#06x}",
+            Retries(u32)           = "failed after {0} retries",
+            Empty()                = "empty",
+            Name(String)           = "invalid name {0}",
+            Path(String)           = "invalid path {0}",
+        }
+        let timeout = MyError::Timeout(Duration::from_secs(3));
+        assert_eq!("timed out after 3s", timeout.to_string());
+        assert!(timeout.source().is_none());
+        assert_eq!("x at line 7", MyError::Position(7, "x".into()).to_string());
+        assert_eq!("1 then 2", MyError::Implicit(1, 2).to_string());
+        assert_eq!("code 0x00ff", MyError::Code(255).to_string());
+        assert_eq!("empty", MyError::Empty().to_string());
+
+        assert_eq!("failed after 3 retries", MyError::Retries(3).to_string());
+        assert_eq!("invalid path x", MyError::Path("x".into()).to_string());
+        assert!(MyError::Name("x".into()).source().is_none());
+
+        // Single values that implement Error are sources, but only `#[from]` implements the conversion
+        let io_err = MyError::Io(io::Error::from(io::ErrorKind::NotFound));
+        assert_eq!("io failure", io_err.to_string());
+        assert_eq!(
+            io::Error::from(io::ErrorKind::NotFound).to_string(),
+            io_err.source().unwrap().to_string()
+        );
+        let format: MyError = std::fmt::Error.into();
+        assert!(format.source().is_some());
+    }
+
+    #[test]
+    fn boxed_tuple_source() {
+        use std::error::Error;
+        custom_error! {MyError Other(Box<dyn Error + Send + Sync>) = "other: {0}"}
+        let err = MyError::Other("inner".into());
+        assert_eq!("other: inner", err.to_string());
+        assert_eq!("inner", err.source().unwrap().to_string());
+    }
+
+    #[test]
+    fn generic_tuple_variant() {
+        custom_error! {MyError<T> Value(T) = "value: {0}", Other(u8, T) = "{0} {1}"}
+        assert_eq!("value: 42", MyError::Value(42).to_string());
+        assert_eq!("1 x", MyError::Other(1, "x").to_string());
+    }
+
+    #[test]
+    fn generic_bounds() {
+        use std::marker::PhantomData;
+        struct NotDisplayable;
+        custom_error! {MyError<T, U>
+            Phantom{_marker: PhantomData<T>, code: u8} = "code {code}",
+            Values{_values: Vec<T>, count: usize}      = "{count} values",
+            Hex(U)                                     = "{0:#x}",
+        }
+        let err: MyError<NotDisplayable, u8> = MyError::Phantom { _marker: PhantomData, code: 1 };
+        assert_eq!("code 1", err.to_string());
+        let err: MyError<NotDisplayable, u8> = MyError::Values { _values: vec![], count: 2 };
+        assert_eq!("2 values", err.to_string());
+        assert_eq!("0xff", MyError::<NotDisplayable, u8>::Hex(255).to_string());
+    }
+
+    #[test]
+    fn where_clause() {
+        use std::{error::Error, fmt::{self, Debug}};
+        trait Named { fn name(&self) -> &'static str; }
+        #[derive(Debug)]
+        struct Config;
+        impl Named for Config { fn name(&self) -> &'static str { "config" } }
+
+        custom_error! {MyError<'a, T: Named + 'a, E> where E: Error + 'static, T: Debug;
+            Invalid{item: &'a T}                    = @{ format!("invalid {}", item.name()) },
+            Failed{#[source] cause: E, item: &'a T} = "{item:?} failed",
+        }
+        custom_error! {Nested<T: Into<Vec<u8>>> where T: Clone, ; {value: T} = "{value:?}"}
+
+        let err: MyError<Config, fmt::Error> = MyError::Invalid { item: &Config };
+        assert_eq!("invalid config", err.to_string());
+        let err = MyError::Failed { cause: fmt::Error, item: &Config };
+        assert_eq!("Config failed", err.to_string());
+        assert!(err.source().is_some());
+        assert_eq!(r#""x""#, Nested { value: "x" }.to_string());
+    }
+
+    #[test]
+    fn const_generics_and_defaults() {
+        custom_error! {BufError<const N: usize>
+            Overflow{len: usize} = "cannot write {len} bytes in a buffer of {N} bytes",
+            Full([u8; N])        = "full buffer: {0:?}",
+        }
+        custom_error! {MyError<T = String, const CODE: u16 = 404>{value: T} = "{value} ({CODE})"}
+
+        assert_eq!(
+            "cannot write 20 bytes in a buffer of 16 bytes",
+            BufError::<16>::Overflow { len: 20 }.to_string()
+        );
+        assert_eq!("full buffer: [1, 2]", BufError::Full([1, 2]).to_string());
+        let err: MyError = MyError { value: "x".to_string() };
+        assert_eq!("x (404)", err.to_string());
+        assert_eq!("1 (500)", MyError::<u8, 500> { value: 1 }.to_string());
+    }
+
+    #[test]
+    fn tuple_struct_error() {
+        use std::{io, error::Error};
+        custom_error! {MyError(#[from] io::Error) = "io error"}
+        custom_error! {Position(u32, u32) = "at {0}:{1}"}
+        let err = MyError::from(io::Error::from(io::ErrorKind::NotFound));
+        assert_eq!("io error", err.to_string());
+        assert!(err.source().is_some());
+        assert_eq!("at 1:2", Position(1, 2).to_string());
+    }
+
+    #[test]
+    #[allow(deprecated)] // unused fields
     fn lifetime_param_and_type_param() {
         #[derive(Debug)]
         struct MyType<'a,T> {data: &'a str, _y: T}
@@ -0,0 +1,67 @@
+#[macro_use]
+extern crate custom_error;
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::fmt::{self, Write};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+custom_error! {MyError
+    Named{name: &'static str, code: u32, ratio: f64} = "{name:?} failed with {code:#x} ({ratio:.2})",
+    Unused{used: u8, _unused: u8}                    = "{used}",
+    Tuple(u8, &'static str)                          = "{1} {0:>4}",
+    Unit                                             = "unit"
+}
+
+// The fields of generic errors are formatted through GenericField
+custom_error! {GenericError<T, U>
+    Named{name: T, code: U, ratio: f64} = "{name:?} failed with {code:#x} ({ratio:.2})",
+    Tuple(U, T)                         = "{1} {0:>4}",
+    Unit                                = "unit"
+}
+
+struct Discard;
+
+impl Write for Discard {
+    fn write_str(&mut self, _: &str) -> fmt::Result { Ok(()) }
+}
+
+#[test]
+fn display_does_not_allocate() {
+    let errors = [
+        MyError::Named { name: "parser", code: 255, ratio: 0.5 },
+        MyError::Unused { used: 1, _unused: 2 },
+        MyError::Tuple(1, "x"),
+        MyError::Unit,
+    ];
+    let generic_errors = [
+        GenericError::Named { name: "parser", code: 255u32, ratio: 0.5 },
+        GenericError::Tuple(1, "x"),
+        GenericError::Unit,
+    ];
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    for err in &errors {
+        write!(Discard, "{}", err).unwrap();
+    }
+    for err in &generic_errors {
+        write!(Discard, "{}", err).unwrap();
+    }
+    assert_eq!(before, ALLOCATIONS.load(Ordering::Relaxed));
+}
@@ -0,0 +1,485 @@
+#![cfg(test)]
+
+#[macro_use]
+extern crate custom_error;
+
+use custom_error::CustomError;
+use std::backtrace::Backtrace;
+use std::error::Error;
+use std::fmt::{self, Debug};
+use std::io;
+use std::marker::PhantomData;
+use std::num::{ParseFloatError, ParseIntError};
+use std::panic::Location;
+
+#[test]
+fn enum_messages() {
+    #[derive(Debug, CustomError)]
+    enum SantaError {
+        #[error("{name} has been bad {foolishness} times this year")]
+        BadChild { name: String, foolishness: u8 },
+        #[error("The location you indicated is too far from the north pole")]
+        TooFar,
+        #[error("register {0:#04x} holds {1:?} and {}")]
+        Register(u8, Option<u8>),
+        #[error(r#"the "{legs}" legs"#)]
+        InvalidReindeer { legs: u8 },
+    }
+
+    let bad = SantaError::BadChild { name: "Thomas".into(), foolishness: 108 };
+    assert_eq!("Thomas has been bad 108 times this year", bad.to_string());
+    assert_eq!("The location you indicated is too far from the north pole", SantaError::TooFar.to_string());
+    assert_eq!("register 0x0a holds Some(1) and 10", SantaError::Register(10, Some(1)).to_string());
+    assert_eq!(r#"the "3" legs"#, SantaError::InvalidReindeer { legs: 3 }.to_string());
+    assert!(bad.source().is_none());
+}
+
+#[test]
+fn format_specifiers() {
+    #[derive(Debug, CustomError)]
+    enum E {
+        #[error("code {code:#x} {code:08b} {{code}}")]
+        Hex { code: u8 },
+        #[error("[{name:>width$}]")]
+        Width { name: &'static str, width: usize },
+        #[error("[{0:>1$}] [{0:.1$}]")]
+        Tuple(f64, usize),
+        #[error("[{:.*}]")]
+        Precision(usize, f64),
+        #[error("[{1:.*}] [{0}]")]
+        PrecisionOf(usize, f32),
+    }
+
+    assert_eq!("code 0xff 11111111 {code}", E::Hex { code: 255 }.to_string());
+    assert_eq!("[  abc]", E::Width { name: "abc", width: 5 }.to_string());
+    assert_eq!("[1.5] [1.500]", E::Tuple(1.5, 3).to_string());
+    assert_eq!("[1.50]", E::Precision(2, 1.5).to_string());
+    assert_eq!("[0.2] [1]", E::PrecisionOf(1, 0.25).to_string());
+}
+
+#[test]
+fn structs() {
+    #[derive(Debug, CustomError)]
+    #[error("line {line}: unexpected token")]
+    pub struct SyntaxError {
+        line: usize,
+    }
+
+    #[derive(Debug, CustomError)]
+    #[error("invalid value {0}")]
+    struct Invalid(i32);
+
+    #[derive(Debug, CustomError)]
+    #[error("end of file")]
+    struct Eof;
+
+    #[derive(Debug, CustomError)]
+    #[error("unable to parse")]
+    struct Parse(#[from] ParseIntError);
+
+    assert_eq!("line 3: unexpected token", SyntaxError { line: 3 }.to_string());
+    assert_eq!("invalid value -1", Invalid(-1).to_string());
+    assert!(Invalid(-1).source().is_none());
+    assert_eq!("end of file", Eof.to_string());
+    let parse = Parse::from("x".parse::<u8>().unwrap_err());
+    assert_eq!("invalid digit found in string", parse.source().unwrap().to_string());
+}
+
+#[test]
+fn sources_and_conversions() {
+    #[derive(Debug, CustomError)]
+    enum ConfigError {
+        #[error("unable to read {path}")]
+        Read { #[source] cause: io::Error, path: String },
+        #[error("invalid number")]
+        Parse { #[from] inner: ParseIntError },
+        #[error("invalid float")]
+        Float { source: ParseFloatError },
+        #[error("formatting failed")]
+        Format(fmt::Error),
+        #[error("invalid environment variable")]
+        Env(#[from] std::env::VarError),
+    }
+
+    let read = ConfigError::Read { cause: io::ErrorKind::NotFound.into(), path: "a".into() };
+    assert_eq!("unable to read a", read.to_string());
+    assert!(read.source().is_some());
+
+    let parse: ConfigError = "x".parse::<u8>().unwrap_err().into();
+    assert_eq!("invalid number", parse.to_string());
+    assert_eq!("invalid digit found in string", parse.source().unwrap().to_string());
+
+    let float = ConfigError::from("x".parse::<f32>().unwrap_err());
+    assert_eq!("invalid float literal", float.source().unwrap().to_string());
+
+    assert!(ConfigError::Format(fmt::Error).source().is_some());
+    assert!(ConfigError::from(std::env::VarError::NotPresent).source().is_some());
+}
+
+#[test]
+fn tuple_variants_without_conversion() {
+    #[derive(Debug, CustomError)]
+    enum NetworkError {
+        #[error("timed out after {0}s")]
+        Timeout(u64),
+        #[error("failed after {0} retries")]
+        Retries(u64),
+        #[error("unknown host {0}")]
+        Host(String),
+        #[error("invalid address {0}")]
+        Address(String),
+    }
+
+    assert_eq!("timed out after 3s", NetworkError::Timeout(3).to_string());
+    assert_eq!("failed after 2 retries", NetworkError::Retries(2).to_string());
+    assert_eq!("invalid address x", NetworkError::Address("x".into()).to_string());
+    assert!(NetworkError::Host("x".into()).source().is_none());
+}
+
+#[test]
+fn conversion_with_other_fields() {
+    #[derive(Debug, CustomError)]
+    enum ReadError {
+        #[error("unable to read {path:?}")]
+        Io {
+            source: io::Error,
+            #[default(None)]
+            path: Option<String>,
+        },
+        #[error("invalid base {radix} number at {location}")]
+        Number {
+            source: ParseIntError,
+            #[default = 10]
+            radix: u32,
+            location: &'static Location<'static>,
+        },
+        #[error("internal error")]
+        Internal { #[from] cause: ParseFloatError, backtrace: Backtrace, #[default(vec![1])] data: Vec<u8> },
+    }
+
+    let io = ReadError::from(io::Error::from(io::ErrorKind::NotFound));
+    assert_eq!("unable to read None", io.to_string());
+    assert!(io.backtrace().is_none());
+
+    let line = line!() + 1;
+    let number = ReadError::from("x".parse::<u8>().unwrap_err());
+    assert_eq!(format!("invalid base 10 number at {}:{}:18", file!(), line), number.to_string());
+
+    let internal = ReadError::from("x".parse::<f32>().unwrap_err());
+    assert!(internal.backtrace().is_some());
+    match internal {
+        ReadError::Internal { data, .. } => assert_eq!(vec![1], data),
+        _ => unreachable!(),
+    }
+}
+
+#[test]
+fn transparent() {
+    #[derive(Debug, CustomError)]
+    #[error("invalid context")]
+    struct Context {
+        source: ParseIntError,
+    }
+
+    #[derive(Debug, CustomError)]
+    enum MyError {
+        #[error(transparent)]
+        Io { source: io::Error },
+        #[error(transparent)]
+        Parse(#[from] ParseIntError),
+        #[error(transparent)]
+        Boxed { inner: Box<dyn Error + Send + Sync> },
+    }
+
+    let io = MyError::from(io::Error::other("disk full"));
+    assert_eq!("disk full", io.to_string());
+    assert!(io.source().is_none());
+
+    let wrapper = MyError::Boxed { inner: Box::new(Context { source: "x".parse::<u8>().unwrap_err() }) };
+    assert_eq!("invalid context", wrapper.to_string());
+    assert_eq!("invalid digit found in string", wrapper.source().unwrap().to_string());
+
+    let parse = MyError::from("x".parse::<u8>().unwrap_err());
+    assert_eq!("invalid digit found in string", parse.to_string());
+    assert!(parse.source().is_none());
+
+    let boxed = MyError::Boxed { inner: "boxed error".into() };
+    assert_eq!("boxed error", boxed.to_string());
+}
+
+#[test]
+fn generics() {
+    #[derive(Debug, CustomError)]
+    enum MyError<'a, T, U: Clone, const N: usize>
+    where
+        U: Debug,
+    {
+        #[error("{value} is invalid")]
+        Invalid { value: T, input: &'a str },
+        #[error("{0:?} at {1}")]
+        Other(U, usize),
+        #[error("full buffer")]
+        Full([u8; N]),
+        #[error("unused")]
+        #[allow(dead_code)]
+        Unused(PhantomData<T>),
+    }
+
+    let invalid: MyError<u8, (), 4> = MyError::Invalid { value: 42, input: "42" };
+    assert_eq!("42 is invalid", invalid.to_string());
+    assert!(invalid.source().is_none());
+    assert_eq!("() at 3", MyError::<u8, (), 4>::Other((), 3).to_string());
+    assert_eq!("full buffer", MyError::<u8, (), 2>::Full([0; 2]).to_string());
+
+    #[derive(Debug, CustomError)]
+    #[error("error in {context}")]
+    struct Wrapped<E: Error + 'static> {
+        context: &'static str,
+        #[source]
+        error: E,
+    }
+
+    let wrapped = Wrapped { context: "test", error: fmt::Error };
+    assert_eq!("error in test", wrapped.to_string());
+    assert_eq!("an error occurred when formatting an argument", wrapped.source().unwrap().to_string());
+}
+
+#[test]
+fn same_behavior_as_macro() {
+    custom_error! {MacroError
+        Io{source: io::Error, path: String} = "unable to read {path}",
+        Parse(#[from] ParseIntError)        = "invalid number {0}",
+    }
+
+    #[derive(Debug, CustomError)]
+    enum DeriveError {
+        #[error("unable to read {path}")]
+        Io { source: io::Error, path: String },
+        #[error("invalid number {0}")]
+        Parse(#[from] ParseIntError),
+    }
+
+    fn describe<E: Error>(error: E) -> (String, Option<String>) {
+        (error.to_string(), error.source().map(|source| source.to_string()))
+    }
+
+    let parse = || "x".parse::<u8>().unwrap_err();
+    assert_eq!(describe(MacroError::from(parse())), describe(DeriveError::from(parse())));
+    let io = || io::Error::from(io::ErrorKind::NotFound);
+    assert_eq!(
+        describe(MacroError::Io { source: io(), path: "a".into() }),
+        describe(DeriveError::Io { source: io(), path: "a".into() })
+    );
+}
+
+#[test]
+fn exit_codes() {
+    use custom_error::{Exit, Main};
+    use std::process::{ExitCode, Termination};
+
+    #[derive(Debug, CustomError)]
+    enum CliError {
+        #[error("unknown argument {arg}")]
+        #[exit = 64]
+        Usage { arg: String },
+        #[error("unexpected error")]
+        Other,
+    }
+
+    #[derive(Debug, CustomError)]
+    #[error("end of file")]
+    struct Eof;
+
+    assert_eq!(64, CliError::Usage { arg: "-x".into() }.exit_code());
+    assert_eq!(1, Exit::exit_code(&CliError::Other));
+    assert_eq!(1, Eof.exit_code());
+    assert!(ExitCode::from(64) == Main(Err(CliError::Usage { arg: "-x".into() })).report());
+    assert!(ExitCode::FAILURE == Main(Err(Eof)).report());
+}
+
+#[test]
+fn accessors() {
+    custom_error! {
+        #[accessors]
+        MyError
+            Io{source: io::Error}            = "input/output error",
+            NotFound(String)                 = "{0} not found",
+            HTTPError{status: u16, body: u8} = "status {status} ({body})",
+            #[cfg(any())]
+            Disabled{x: NotAType}            = "disabled",
+            Unit                             = "unit",
+            #[deprecated(note = "use Unit instead")]
+            Old(u8)                          = "old",
+    }
+
+    let not_found = MyError::NotFound("x".into());
+    assert!(not_found.is_not_found());
+    assert!(!not_found.is_io());
+    assert!(MyError::HTTPError { status: 404, body: 0 }.is_http_error());
+    assert!(MyError::Unit.is_unit());
+    assert!(!MyError::Unit.is_old());
+    assert_eq!(Some(&"x".to_string()), not_found.as_not_found());
+    assert!(not_found.as_io().is_none());
+    assert_eq!("x", not_found.into_not_found().unwrap());
+
+    let io = MyError::from(io::Error::other("disk full"));
+    assert_eq!("disk full", io.as_io().unwrap().to_string());
+    let io = io.into_not_found().unwrap_err();
+    assert_eq!(io::ErrorKind::Other, io.into_io().unwrap().kind());
+
+    custom_error! {#[accessors] Generic<T: Clone> Value{value: T} = "{value}", Other = "other"}
+    assert_eq!(Some(&1), Generic::Value { value: 1 }.as_value());
+    assert!(Generic::<u8>::Other.is_other());
+}
+
+#[test]
+fn accessors_with_result_alias() {
+    // The Result alias of the module must not be used by the generated methods
+    mod parser {
+        custom_error! {
+            #[result]
+            #[accessors]
+            pub ParseError
+                Empty           = "empty input",
+                Invalid(char)   = "invalid character {0:?}",
+        }
+
+        pub fn parse(input: &str) -> Result<u32> {
+            if let Some(c) = input.chars().find(|c| !c.is_ascii_digit()) {
+                bail!(Invalid(c));
+            }
+            input.parse().map_err(|_| ParseError::Empty)
+        }
+    }
+
+    let err = parser::parse("1x").unwrap_err();
+    assert_eq!(Some(&'x'), err.as_invalid());
+    assert_eq!('x', err.into_invalid().unwrap());
+    assert!(parser::parse("").unwrap_err().into_invalid().unwrap_err().is_empty());
+}
+
+#[test]
+fn constructors() {
+    custom_error! {
+        #[constructors]
+        #[accessors]
+        SantaError
+            BadChild{name: String, foolishness: u8} = "{name} has been bad {foolishness} times this year",
+            TooFar                                  = "The location you indicated is too far from the north pole",
+            Lost(&'static str, Vec<u8>)             = "{0} is lost ({1:?})",
+            Io{source: io::Error, location: &'static Location<'static>} = "input/output error at {location}",
+            #[deprecated(note = "use TooFar instead")]
+            Away(u32)                               = "{0} km away",
+    }
+
+    let bad = SantaError::bad_child("Thomas", 108);
+    assert_eq!("Thomas has been bad 108 times this year", bad.to_string());
+    assert!(SantaError::too_far().is_too_far());
+    assert!(!SantaError::too_far().is_away());
+    assert_eq!("Rudolph is lost ([1])", SantaError::lost("Rudolph", vec![1]).to_string());
+    let err: Result<(), SantaError> = SantaError::bad_child_err(String::from("Thomas"), 1);
+    assert!(err.unwrap_err().is_bad_child());
+
+    let line = line!() + 1;
+    let io = SantaError::io(io::ErrorKind::NotFound);
+    assert_eq!(format!("input/output error at {}:{}:14", file!(), line), io.to_string());
+    assert!(io.source().is_some());
+
+    custom_error! {#[constructors] Generic<T>{value: T, name: String} = "{name}: {value}"}
+    assert_eq!("x: 1", Generic::new(1, "x").to_string());
+    assert!(Generic::<u8>::new_err::<()>(1, "x").is_err());
+}
+
+#[test]
+fn constructors_with_result_alias() {
+    // The Result alias of the module must not be used by the generated constructors
+    mod lines {
+        custom_error! {
+            #[result]
+            #[constructors]
+            pub LineError
+                Empty                           = "empty line",
+                TooLong{line: u32, len: usize}  = "line {line} has {len} characters",
+        }
+
+        pub fn check(line: u32, text: &str) -> Result<&str> {
+            if text.is_empty() {
+                return LineError::empty_err();
+            }
+            ensure!(text.len() < 80, TooLong { line, len: text.len() });
+            Ok(text)
+        }
+    }
+
+    assert_eq!("empty line", lines::check(1, "").unwrap_err().to_string());
+    let err: lines::Result<()> = lines::LineError::too_long_err(2u32, 100usize);
+    assert_eq!("line 2 has 100 characters", err.unwrap_err().to_string());
+}
+
+#[test]
+fn context_selectors() {
+    use custom_error::{OptionExt, ResultExt};
+    use std::path::{Path, PathBuf};
+
+    custom_error! {
+        #[context]
+        ConfigError
+            ReadConfig{source: io::Error, path: PathBuf} = "unable to read {path:?}",
+            Parse{source: ParseIntError, line: u32, location: &'static Location<'static>} = "line {line} at {location}",
+            MissingKey{key: String}                      = "missing key {key}",
+            Refused(String, u16)                         = "{0} refused ({1})",
+            Empty                                        = "empty configuration",
+            #[deprecated(note = "use MissingKey instead")]
+            Missing{key: String}                         = "missing {key}",
+    }
+
+    fn read(path: &Path) -> Result<Vec<u8>, ConfigError> {
+        std::fs::read(path).context(ReadConfigCtx { path })
+    }
+
+    let err = read(Path::new("/nonexistent")).unwrap_err();
+    assert_eq!(r#"unable to read "/nonexistent""#, err.to_string());
+    assert_eq!(io::ErrorKind::NotFound, err.source().unwrap().downcast_ref::<io::Error>().unwrap().kind());
+
+    let line = line!() + 1;
+    let err = "x".parse::<u8>().context(ParseCtx { line: 3 }).unwrap_err();
+    assert_eq!(format!("line 3 at {}:{}:33", file!(), line), err.to_string());
+
+    let value: Result<u8, ConfigError> = None.context(MissingKeyCtx { key: "name" });
+    assert_eq!("missing key name", value.unwrap_err().to_string());
+    assert_eq!(Ok(1), Some(1).context(EmptyCtx).map_err(|err: ConfigError| err.to_string()));
+    let refused: Result<(), ConfigError> = None.context(RefusedCtx("server", 403));
+    let missing: Result<(), ConfigError> = None.context(MissingCtx { key: "x" });
+    assert_eq!("missing x", missing.unwrap_err().to_string());
+    assert_eq!("server refused (403)", refused.unwrap_err().to_string());
+
+    custom_error! {#[context] Wrapped<T>{source: io::Error, value: T} = "{value}"}
+    let wrapped: Result<(), Wrapped<String>> = Err(io::ErrorKind::Other.into()).context(WrappedCtx { value: "x" });
+    assert_eq!("x", wrapped.unwrap_err().to_string());
+}
+
+/// The items of the crate under another path, like a renamed dependency
+mod errors {
+    pub use custom_error::*;
+}
+
+#[test]
+fn renamed_crate() {
+    #[derive(Debug, errors::CustomError)]
+    #[custom_error(crate = ::errors)]
+    enum RenamedError {
+        #[error("unable to read")]
+        Io { #[from] source: io::Error, location: &'static Location<'static> },
+        #[error(transparent)]
+        Parse(ParseIntError),
+        #[error("interrupted")]
+        #[exit = 130]
+        Interrupted,
+    }
+
+    let io = RenamedError::from(io::Error::from(io::ErrorKind::NotFound));
+    assert_eq!("unable to read", io.to_string());
+    assert!(io.source().is_some());
+    assert_eq!("invalid digit found in string", RenamedError::Parse("x".parse::<u8>().unwrap_err()).to_string());
+    assert_eq!(130, RenamedError::Interrupted.exit_code());
+}
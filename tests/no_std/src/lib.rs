@@ -0,0 +1,104 @@
+#![no_std]
+
+#[macro_use]
+extern crate custom_error;
+#[cfg(feature = "alloc")]
+extern crate alloc;
+#[cfg(test)]
+extern crate std;
+
+#[cfg(feature = "alloc")]
+use alloc::{boxed::Box, string::String};
+use core::fmt;
+use core::num::ParseIntError;
+use core::panic::Location;
+
+custom_error! {pub FirmwareError
+    Fmt{source: fmt::Error}                    = "unable to format the report",
+    Parse{source: ParseIntError, at: &'static Location<'static>} = "invalid register value at {at}",
+    Register(u8, u32)                          = "register {0:#04x} holds {1:#x}",
+    Sensor{name: &'static str, reading: i32}   = "sensor {name} read {reading:+}",
+    Halted                                     = "the device is halted"
+}
+
+#[cfg(feature = "alloc")]
+custom_error! {pub AllocError
+    Named{name: String}                        = "{name} failed",
+    Boxed{inner: Box<dyn core::error::Error + Send + Sync>} = transparent,
+}
+
+custom_error! {pub Overflow{len: usize} = "buffer overflow after {len} bytes"}
+
+/// Parses a register value, using `?` to convert the error.
+pub fn parse_register(value: &str) -> Result<u32, FirmwareError> {
+    Ok(value.parse::<u32>()?)
+}
+
+/// Writes an error into a fixed-size buffer, without allocating.
+pub fn render<'a, E: fmt::Display>(error: &E, buffer: &'a mut [u8]) -> Result<&'a str, Overflow> {
+    struct Cursor<'a> {
+        buffer: &'a mut [u8],
+        len: usize,
+    }
+
+    impl<'a> fmt::Write for Cursor<'a> {
+        fn write_str(&mut self, s: &str) -> fmt::Result {
+            let end = self.len + s.len();
+            if end > self.buffer.len() {
+                return Err(fmt::Error);
+            }
+            self.buffer[self.len..end].copy_from_slice(s.as_bytes());
+            self.len = end;
+            Ok(())
+        }
+    }
+
+    let mut cursor = Cursor { buffer, len: 0 };
+    fmt::write(&mut cursor, format_args!("{}", error)).map_err(|_| Overflow { len: cursor.len })?;
+    let Cursor { buffer, len } = cursor;
+    Ok(core::str::from_utf8(&buffer[..len]).unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::error::Error;
+    use std::string::ToString;
+
+    #[test]
+    fn display_without_allocator() {
+        let mut buffer = [0u8; 32];
+        assert_eq!(
+            "register 0x0a holds 0xff",
+            render(&FirmwareError::Register(10, 255), &mut buffer).unwrap()
+        );
+        assert_eq!(
+            "buffer overflow after 0 bytes",
+            render(&FirmwareError::Halted, &mut [0u8; 4]).unwrap_err().to_string()
+        );
+    }
+
+    #[test]
+    fn sources_and_conversions() {
+        let parse = parse_register("x").unwrap_err();
+        assert!(parse.to_string().starts_with("invalid register value at "));
+        assert_eq!("invalid digit found in string", parse.source().unwrap().to_string());
+        assert!(FirmwareError::from(fmt::Error).source().is_some());
+        assert!(FirmwareError::Halted.source().is_none());
+    }
+
+    #[test]
+    fn display_fields() {
+        let sensor = FirmwareError::Sensor { name: "temp", reading: 21 };
+        let mut buffer = [0u8; 32];
+        assert_eq!("sensor temp read +21", render(&sensor, &mut buffer).unwrap());
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn alloc_fields() {
+        assert_eq!("sensor failed", AllocError::Named { name: "sensor".into() }.to_string());
+        let boxed = AllocError::Boxed { inner: Box::new(FirmwareError::Halted) };
+        assert_eq!("the device is halted", boxed.to_string());
+    }
+}